@@ -17,7 +17,8 @@ import * as dotenv from "dotenv";
 import { getFigureName } from "./figures.ts";
 
 interface RoastIndex {
-  duel_id: string;
+  duel_id: string | null;
+  royale_id: string | null;
   turn: number;
   current_figure: string;
   next_figure: string;
@@ -158,7 +159,10 @@ Severity of the roast: ${severity}.
   });
   const roast = res.choices[0].message.content.trim();
 
-  const path = `./roast-${index.duel_id}-${index.turn}.txt`;
+  const isRoyale = index.royale_id !== null;
+  const game = isRoyale ? `royale ${index.royale_id}` : `duel ${index.duel_id}`;
+
+  const path = `./roast-${index.royale_id ?? index.duel_id}-${index.turn}.txt`;
   fs.writeFileSync(path, roast);
 
   const file = await filesFromPaths([path]);
@@ -167,9 +171,11 @@ Severity of the roast: ${severity}.
 
   await account!.functionCall({
     contractId: DUELS_CONTRACT_ID,
-    methodName: "set_roast",
+    methodName: isRoyale ? "set_royale_roast" : "set_roast",
     args: {
-      duel_id: index.duel_id,
+      ...(isRoyale
+        ? { royale_id: index.royale_id }
+        : { duel_id: index.duel_id }),
       turn: index.turn,
       roast_cid: roast_cid.toString(),
     },
@@ -177,7 +183,7 @@ Severity of the roast: ${severity}.
     deposit: 0,
   });
 
-  console.log(`Roast generated for duel ${index.duel_id}! "${roast}"`);
+  console.log(`Roast generated for ${game}! "${roast}"`);
 }
 
 async function createDuelThread(duel: Duel) {
@@ -0,0 +1,243 @@
+use crate::*;
+use u256::U256;
+
+// Each bettor is a storage record until they claim, so pools are capped
+const MAX_BETS_PER_DUEL: u32 = 100;
+// Bets must be at least this fraction of the minimum duel stake
+const MIN_BET_DIVISOR: u128 = 10;
+
+// Pro rata payouts multiply two 24-decimal amounts, which overflows u128
+mod u256 {
+    #![allow(
+        deprecated,
+        semicolon_in_expressions_from_non_local_macros,
+        clippy::all
+    )]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+#[near_bindgen]
+impl DuelManagerContract {
+    pub fn get_odds(&self, duel_id: U128) -> Option<Odds> {
+        let pool = self.bets.get(&duel_id.0)?;
+        let fee_percent = self.duels[&duel_id.0].fee_percent;
+        Some(Odds {
+            pool_a: pool.pool_a,
+            pool_b: pool.pool_b,
+            return_a: Self::bet_return(pool, Side::PlayerA, fee_percent),
+            return_b: Self::bet_return(pool, Side::PlayerB, fee_percent),
+            result: pool.result,
+        })
+    }
+
+    // Unclaimed bets, newest duel first. Pass the last duel id of a page as `from_id` to get the
+    // next one.
+    pub fn get_account_bets(
+        &self,
+        account_id: AccountId,
+        from_id: Option<U128>,
+        count: usize,
+    ) -> Vec<Bet> {
+        let end = match from_id {
+            Some(from_id) => Bound::Excluded((account_id.clone(), from_id.0)),
+            None => Bound::Included((account_id.clone(), u128::MAX)),
+        };
+
+        self.account_bets
+            .range((Bound::Included((account_id.clone(), 0)), end))
+            .rev()
+            .take(count)
+            .map(|(_, bet)| bet.clone())
+            .collect()
+    }
+
+    #[payable]
+    pub fn place_bet(&mut self, duel_id: U128, side: Side, amount: U128) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._place_bet(sender, duel_id, side, amount);
+    }
+
+    #[payable]
+    pub fn claim_bet(&mut self, duel_id: U128) -> U128 {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._claim_bet(sender, duel_id)
+    }
+
+    pub(crate) fn _place_bet(
+        &mut self,
+        sender: AccountId,
+        duel_id: U128,
+        side: Side,
+        amount: U128,
+    ) {
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.");
+        assert!(
+            duel.status() == DuelStatus::Active,
+            "Bets can only be placed on active duels."
+        );
+        // The first turn shows who is ahead, so betting closes before it
+        assert!(
+            duel.turns.is_empty(),
+            "Bets close once the first turn is taken."
+        );
+        assert!(
+            !duel.accounts().any(|account_id| *account_id == sender),
+            "Players cannot bet on their own duel."
+        );
+        assert!(
+            amount.0 >= self.tokens[&duel.token].min_stake.0 / MIN_BET_DIVISOR,
+            "Bet is below the minimum."
+        );
+
+        let balance = self
+            .stakes
+            .entry((sender.clone(), duel.token.clone()))
+            .or_insert(0);
+        assert!(*balance >= amount.0, "Insufficient balance");
+        *balance -= amount.0;
+
+        let pool = self
+            .bets
+            .entry(duel_id.0)
+            .or_insert_with(|| BetPool::new(duel.token.clone()));
+        // Adding to a bet keeps a single record per bettor
+        match self.account_bets.get_mut(&(sender.clone(), duel_id.0)) {
+            Some(bet) => {
+                assert!(bet.side == side, "You already bet on the other side.");
+                bet.amount.0 += amount.0;
+            }
+            None => {
+                assert!(
+                    pool.bettors() < MAX_BETS_PER_DUEL,
+                    "This duel has reached the maximum number of bets."
+                );
+                *pool.side_bettors(side) += 1;
+                let bet = Bet {
+                    account_id: sender.clone(),
+                    duel_id,
+                    side,
+                    amount,
+                    creation_time: env::block_timestamp(),
+                };
+                self.account_bets.insert((sender.clone(), duel_id.0), bet);
+            }
+        }
+        pool.side_pool(side).0 += amount.0;
+
+        env::log_str(&format!(
+            "{} placed a bet of {} on duel {}.",
+            sender, amount.0, duel_id.0
+        ));
+    }
+
+    // Losing bets can be claimed too, which just clears them
+    fn _claim_bet(&mut self, sender: AccountId, duel_id: U128) -> U128 {
+        let bet = self
+            .account_bets
+            .remove(&(sender.clone(), duel_id.0))
+            .expect("Bet not found.");
+        let pool = self.bets.get_mut(&duel_id.0).unwrap();
+        let result = pool.result.expect("The duel is not settled yet.");
+        let token = pool.token.clone();
+        *pool.side_bettors(bet.side) -= 1;
+
+        let mut dust = 0;
+        let amount = match result {
+            BetResult::Refunded => bet.amount.0,
+            BetResult::Won(side) if side == bet.side => {
+                let winning = U256::from(pool.side_total(side));
+                let amount =
+                    (U256::from(bet.amount.0) * U256::from(pool.payable.0) / winning).as_u128();
+                pool.paid.0 += amount;
+                // The last winner leaves the rounding dust behind
+                if *pool.side_bettors(side) == 0 {
+                    dust = pool.payable.0 - pool.paid.0;
+                }
+                amount
+            }
+            BetResult::Won(_) => 0,
+        };
+        if pool.bettors() == 0 {
+            self.bets.remove(&duel_id.0);
+        }
+
+        if amount > 0 {
+            *self
+                .stakes
+                .entry((sender.clone(), token.clone()))
+                .or_insert(0) += amount;
+        }
+        if dust > 0 {
+            self.burn(&token, U128(dust));
+        }
+
+        env::log_str(&format!(
+            "{} claimed {} from bets on duel {}.",
+            sender, amount, duel_id.0
+        ));
+        U128(amount)
+    }
+
+    // Records the result and burns the duel's fee from the pool. Bettors then claim their own
+    // winnings, so settling costs the same however many bets there are.
+    pub(crate) fn settle_bets(&mut self, duel: &Duel, winner: Winner) {
+        let duel_id = duel.id.0;
+        let side = match winner {
+            Winner::PlayerA => Side::PlayerA,
+            Winner::PlayerB => Side::PlayerB,
+            Winner::Draw => return self.refund_bets(duel_id),
+        };
+        let Some(pool) = self.bets.get_mut(&duel_id) else {
+            return;
+        };
+
+        // Without bets on both sides there is nothing to win
+        let total = pool.total();
+        let winning = pool.side_total(side);
+        if winning == 0 || winning == total {
+            return self.refund_bets(duel_id);
+        }
+
+        let fee = total * duel.fee_percent as u128 / 100;
+        pool.result = Some(BetResult::Won(side));
+        pool.payable = U128(total - fee);
+        let token = pool.token.clone();
+        self.burn(&token, U128(fee));
+
+        env::log_str(&format!("Bets on duel {} settled.", duel_id));
+    }
+
+    pub(crate) fn refund_bets(&mut self, duel_id: u128) {
+        let Some(pool) = self.bets.get_mut(&duel_id) else {
+            return;
+        };
+
+        pool.result = Some(BetResult::Refunded);
+        env::log_str(&format!("Bets on duel {} refunded.", duel_id));
+    }
+
+    // What a winning bet on `side` would return per 10,000 staked at the current pools
+    fn bet_return(pool: &BetPool, side: Side, fee_percent: u8) -> Option<u32> {
+        let total = pool.total();
+        let backing = pool.side_total(side);
+        if backing == 0 || backing == total {
+            return None;
+        }
+
+        let payable = total - total * fee_percent as u128 / 100;
+        let ratio = U256::from(payable) * U256::from(10_000) / U256::from(backing);
+        Some(ratio.min(U256::from(u32::MAX)).as_u32())
+    }
+}
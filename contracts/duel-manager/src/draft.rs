@@ -0,0 +1,78 @@
+use crate::*;
+
+#[near_bindgen]
+impl DuelManagerContract {
+    #[payable]
+    pub fn ban_figure(&mut self, duel_id: U128, figure: FigureId) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._draft(sender, duel_id, figure, DraftStep::Ban);
+    }
+
+    #[payable]
+    pub fn pick_figure(&mut self, duel_id: U128, figure: FigureId) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._draft(sender, duel_id, figure, DraftStep::Pick);
+    }
+
+    fn _draft(&mut self, sender: AccountId, duel_id: U128, figure: FigureId, step: DraftStep) {
+        let mut duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+        let (expected, is_player_a) = duel.draft_step().expect("Duel is not drafting.");
+
+        let drafter = if is_player_a {
+            &duel.player_a
+        } else {
+            duel.player_b.as_ref().unwrap()
+        };
+        assert!(*drafter == sender, "Invalid sender.");
+        assert!(
+            step == expected,
+            "{}",
+            match expected {
+                DraftStep::Ban => "Bans are still in progress.",
+                DraftStep::Pick => "Bans are over.",
+            }
+        );
+        self.assert_figure(&figure);
+        assert!(!duel.bans.contains(&figure), "Figure is banned.");
+
+        match step {
+            DraftStep::Ban => duel.bans.push(figure.clone()),
+            DraftStep::Pick if is_player_a => duel.figure_a = Some(figure.clone()),
+            DraftStep::Pick => {
+                assert!(
+                    duel.figure_a.as_ref() != Some(&figure),
+                    "Historical figure already selected."
+                );
+                duel.figure_b = Some(figure.clone());
+            }
+        }
+
+        // Each step gets a fresh deadline, and the duel can begin once both figures are in
+        if duel.draft_step().is_some() {
+            duel.draft_deadline = Some(env::block_timestamp() + duel.draft_timeout);
+        } else {
+            duel.draft_deadline = None;
+            self.load_figures(&mut duel);
+        }
+        self.duels.insert(duel_id.0, duel);
+
+        let action = match step {
+            DraftStep::Ban => "banned",
+            DraftStep::Pick => "picked",
+        };
+        env::log_str(&format!(
+            "{} {} {} in duel {}.",
+            sender, action, figure, duel_id.0
+        ));
+    }
+}
@@ -1,43 +1,248 @@
 use near_sdk::near;
-use strum_macros::EnumIter;
+use std::cmp;
+
+pub const XP_PER_LEVEL: u32 = 100;
+pub const MAX_LEVEL: u8 = 10;
+// Every few levels add a point to each stat, so a maxed figure gains at most +2
+const LEVELS_PER_BONUS: u8 = 4;
+// Caps admin-set stats so hit points and boosted damage stay well within a u8
+pub const MAX_STAT: u8 = 20;
+
+// Stable short id of a figure, e.g. "SunTzu"
+pub type FigureId = String;
 
 #[near(serializers = [json, borsh])]
-#[derive(Copy, Clone, PartialEq, Eq, Hash, EnumIter)]
-pub enum HistoricalFigure {
-    JuliusCaesar,
-    WilliamShakespeare,
-    GenghisKhan,
-    NapoleonBonaparte,
-    MarkTwain,
-    SunTzu,
-    Socrates,
-    WinstonChurchill,
-    MarieAntoinette,
-    LeonardoDaVinci,
-    OscarWilde,
-    AttilaTheHun,
-    TheodoreRoosevelt,
-    BenjaminFranklin,
-    HannibalBarca,
-    Confucius,
-    VladTheImpaler,
-    NiccoloMachiavelli,
-    KarlMarx,
-    FriedrichNietzsche,
-    JoanOfArc,
-    AndrewJackson,
-    OttoVonBismarck,
-    SalvadorDali,
-    HarrietTubman,
-    NelsonMandela,
-    JohnFKennedy,
-    MartinLutherKingJr,
-    MalcolmX,
-    FrederickDouglass,
+#[derive(Copy, Clone, PartialEq)]
+pub enum SignatureMove {
+    // The opponent's reply counts as weak against this roast
+    ReadTheEnemy,
+    // Always counts as strong and ignores the weakness penalty
+    BrutalBurst,
+    // Rolls damage twice and keeps the better roll
+    Flourish,
+    // Can't roll below half the maximum damage
+    Rally,
+    // Adds half of the opponent's last damage
+    Ridicule,
+    // Rolls damage three times and keeps the best roll
+    VeniVidiVici,
+    // Counts as strong and can't be dodged
+    GrandBattery,
+    // Rolls with the figure's highest stat, whatever the style
+    TallTale,
+    // The opponent's reply counts as weak, and this roast ignores the weakness penalty
+    SocraticIrony,
+    // Always rolls the maximum damage, but can't land a critical hit
+    Masterpiece,
+    // Adds 3 damage
+    Epigram,
+    // Rolls up to ten more damage, but still takes the weakness penalty
+    Scourge,
+    // Can't be dodged or roll below half the maximum damage
+    BigStick,
+    // Rolls damage twice, keeps the better roll and ignores the weakness penalty
+    Almanac,
+    // Counts as strong and rolls damage twice, keeping the better roll
+    Encirclement,
+    // Ignores the weakness penalty and can't roll below half the maximum damage
+    Analects,
+    // Always lands a critical hit unless dodged
+    Impale,
+    // The opponent's reply counts as weak, and this roast can't be dodged
+    Intrigue,
+    // Adds all of the opponent's last damage
+    Redistribution,
+    // Rolls with the figure's highest stat and ignores the weakness penalty
+    WillToPower,
+    // Ignores the weakness penalty and can't be dodged
+    HolyBanner,
+    // Counts as strong and can't roll below half the maximum damage
+    OldHickory,
+    // The opponent's reply counts as weak, and this roast adds 3 damage
+    Realpolitik,
+    // Rolls with the figure's highest stat, twice, keeping the better roll
+    Surrealism,
+    // Can't be dodged and rolls damage twice, keeping the better roll
+    Conductor,
+    // Adds half of the opponent's last damage and can't roll below half the maximum
+    LongWalk,
+    // Counts as strong and adds 2 damage
+    Moonshot,
+    // Adds 3 damage and ignores the weakness penalty
+    Dream,
+    // Rolls with the figure's highest stat and can't be dodged
+    AnyMeans,
+    // Adds half of the opponent's last damage and rolls twice, keeping the better roll
+    Oratory,
+}
+
+// How a signature move changes a roast. Combined with a strong roast, `boost` is at least the
+// strong bonus rather than added to it.
+#[derive(Default)]
+pub struct MoveEffect {
+    // Extra maximum damage
+    pub boost: u8,
+    // Extra dice, keeping the best roll
+    pub rerolls: u8,
+    // Lowest roll, in percent of the maximum damage
+    pub floor_percent: u8,
+    pub bonus: u8,
+    // Share of the opponent's last damage that is added, in percent
+    pub payback_percent: u8,
+    pub ignores_weakness: bool,
+    // The opponent's reply counts as weak against this roast
+    pub reads: bool,
+    pub highest_stat: bool,
+    pub undodgeable: bool,
+    // Forces a critical hit, or rules one out, instead of rolling for it
+    pub critical: Option<bool>,
+}
+
+impl SignatureMove {
+    pub fn effect(self) -> MoveEffect {
+        let strong = MoveEffect {
+            boost: 5,
+            ..Default::default()
+        };
+        match self {
+            ReadTheEnemy => MoveEffect {
+                reads: true,
+                ..Default::default()
+            },
+            BrutalBurst => MoveEffect {
+                ignores_weakness: true,
+                ..strong
+            },
+            Flourish => MoveEffect {
+                rerolls: 1,
+                ..Default::default()
+            },
+            Rally => MoveEffect {
+                floor_percent: 50,
+                ..Default::default()
+            },
+            Ridicule => MoveEffect {
+                payback_percent: 50,
+                ..Default::default()
+            },
+            VeniVidiVici => MoveEffect {
+                rerolls: 2,
+                ..Default::default()
+            },
+            GrandBattery => MoveEffect {
+                undodgeable: true,
+                ..strong
+            },
+            TallTale => MoveEffect {
+                highest_stat: true,
+                ..Default::default()
+            },
+            SocraticIrony => MoveEffect {
+                reads: true,
+                ignores_weakness: true,
+                ..Default::default()
+            },
+            Masterpiece => MoveEffect {
+                floor_percent: 100,
+                critical: Some(false),
+                ..Default::default()
+            },
+            Epigram => MoveEffect {
+                bonus: 3,
+                ..Default::default()
+            },
+            Scourge => MoveEffect {
+                boost: 10,
+                ..Default::default()
+            },
+            BigStick => MoveEffect {
+                undodgeable: true,
+                floor_percent: 50,
+                ..Default::default()
+            },
+            Almanac => MoveEffect {
+                rerolls: 1,
+                ignores_weakness: true,
+                ..Default::default()
+            },
+            Encirclement => MoveEffect {
+                rerolls: 1,
+                ..strong
+            },
+            Analects => MoveEffect {
+                ignores_weakness: true,
+                floor_percent: 50,
+                ..Default::default()
+            },
+            Impale => MoveEffect {
+                critical: Some(true),
+                ..Default::default()
+            },
+            Intrigue => MoveEffect {
+                reads: true,
+                undodgeable: true,
+                ..Default::default()
+            },
+            Redistribution => MoveEffect {
+                payback_percent: 100,
+                ..Default::default()
+            },
+            WillToPower => MoveEffect {
+                highest_stat: true,
+                ignores_weakness: true,
+                ..Default::default()
+            },
+            HolyBanner => MoveEffect {
+                ignores_weakness: true,
+                undodgeable: true,
+                ..Default::default()
+            },
+            OldHickory => MoveEffect {
+                floor_percent: 50,
+                ..strong
+            },
+            Realpolitik => MoveEffect {
+                reads: true,
+                bonus: 3,
+                ..Default::default()
+            },
+            Surrealism => MoveEffect {
+                highest_stat: true,
+                rerolls: 1,
+                ..Default::default()
+            },
+            Conductor => MoveEffect {
+                undodgeable: true,
+                rerolls: 1,
+                ..Default::default()
+            },
+            LongWalk => MoveEffect {
+                payback_percent: 50,
+                floor_percent: 50,
+                ..Default::default()
+            },
+            Moonshot => MoveEffect { bonus: 2, ..strong },
+            Dream => MoveEffect {
+                bonus: 3,
+                ignores_weakness: true,
+                ..Default::default()
+            },
+            AnyMeans => MoveEffect {
+                highest_stat: true,
+                undodgeable: true,
+                ..Default::default()
+            },
+            Oratory => MoveEffect {
+                payback_percent: 50,
+                rerolls: 1,
+                ..Default::default()
+            },
+        }
+    }
 }
 
 #[near(serializers = [json, borsh])]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 pub struct Stats {
     pub wit: u8,
     pub brutality: u8,
@@ -45,189 +250,169 @@ pub struct Stats {
     pub mockery: u8,
 }
 
-impl HistoricalFigure {
-    pub fn stats(self) -> Stats {
-        match self {
-            Self::JuliusCaesar => Stats {
-                wit: 5,
-                brutality: 6,
-                strategy: 6,
-                mockery: 3,
-            },
-            Self::WilliamShakespeare => Stats {
-                wit: 9,
-                brutality: 2,
-                strategy: 5,
-                mockery: 4,
-            },
-            Self::GenghisKhan => Stats {
-                wit: 2,
-                brutality: 10,
-                strategy: 6,
-                mockery: 2,
-            },
-            Self::NapoleonBonaparte => Stats {
-                wit: 4,
-                brutality: 4,
-                strategy: 9,
-                mockery: 3,
-            },
-            Self::MarkTwain => Stats {
-                wit: 10,
-                brutality: 1,
-                strategy: 4,
-                mockery: 5,
-            },
-            Self::SunTzu => Stats {
-                wit: 5,
-                brutality: 2,
-                strategy: 10,
-                mockery: 3,
-            },
-            Self::Socrates => Stats {
-                wit: 8,
-                brutality: 1,
-                strategy: 6,
-                mockery: 5,
-            },
-            Self::WinstonChurchill => Stats {
-                wit: 6,
-                brutality: 3,
-                strategy: 5,
-                mockery: 6,
-            },
-            Self::MarieAntoinette => Stats {
-                wit: 3,
-                brutality: 4,
-                strategy: 4,
-                mockery: 9,
-            },
-            Self::LeonardoDaVinci => Stats {
-                wit: 6,
-                brutality: 2,
-                strategy: 9,
-                mockery: 3,
-            },
-            Self::OscarWilde => Stats {
-                wit: 9,
-                brutality: 1,
-                strategy: 5,
-                mockery: 5,
-            },
-            Self::AttilaTheHun => Stats {
-                wit: 1,
-                brutality: 10,
-                strategy: 5,
-                mockery: 4,
-            },
-            Self::TheodoreRoosevelt => Stats {
-                wit: 4,
-                brutality: 8,
-                strategy: 5,
-                mockery: 3,
-            },
-            Self::BenjaminFranklin => Stats {
-                wit: 7,
-                brutality: 2,
-                strategy: 7,
-                mockery: 4,
-            },
-            Self::HannibalBarca => Stats {
-                wit: 3,
-                brutality: 5,
-                strategy: 10,
-                mockery: 2,
-            },
-            Self::Confucius => Stats {
-                wit: 9,
-                brutality: 1,
-                strategy: 6,
-                mockery: 4,
-            },
-            Self::VladTheImpaler => Stats {
-                wit: 2,
-                brutality: 9,
-                strategy: 5,
-                mockery: 4,
-            },
-            Self::NiccoloMachiavelli => Stats {
-                wit: 5,
-                brutality: 2,
-                strategy: 9,
-                mockery: 4,
-            },
-            Self::KarlMarx => Stats {
-                wit: 6,
-                brutality: 3,
-                strategy: 6,
-                mockery: 5,
-            },
-            Self::FriedrichNietzsche => Stats {
-                wit: 5,
-                brutality: 4,
-                strategy: 5,
-                mockery: 6,
-            },
-            Self::JoanOfArc => Stats {
-                wit: 4,
-                brutality: 7,
-                strategy: 5,
-                mockery: 4,
-            },
-            Self::AndrewJackson => Stats {
-                wit: 3,
-                brutality: 9,
-                strategy: 4,
-                mockery: 4,
-            },
-            Self::OttoVonBismarck => Stats {
-                wit: 4,
-                brutality: 5,
-                strategy: 8,
-                mockery: 3,
-            },
-            Self::SalvadorDali => Stats {
-                wit: 7,
-                brutality: 2,
-                strategy: 4,
-                mockery: 7,
-            },
-            Self::HarrietTubman => Stats {
-                wit: 4,
-                brutality: 6,
-                strategy: 7,
-                mockery: 3,
-            },
-            Self::NelsonMandela => Stats {
-                wit: 6,
-                brutality: 3,
-                strategy: 7,
-                mockery: 4,
-            },
-            Self::JohnFKennedy => Stats {
-                wit: 6,
-                brutality: 4,
-                strategy: 6,
-                mockery: 4,
-            },
-            Self::MartinLutherKingJr => Stats {
-                wit: 8,
-                brutality: 2,
-                strategy: 6,
-                mockery: 4,
-            },
-            Self::MalcolmX => Stats {
-                wit: 7,
-                brutality: 4,
-                strategy: 5,
-                mockery: 4,
-            },
-            Self::FrederickDouglass => Stats {
-                wit: 7,
-                brutality: 3,
-                strategy: 6,
-                mockery: 4,
-            },
+impl Stats {
+    pub fn hit_points(&self) -> u8 {
+        self.wit + self.brutality + self.strategy + self.mockery
+    }
+
+    pub fn highest(&self) -> u8 {
+        cmp::max(
+            cmp::max(self.wit, self.brutality),
+            cmp::max(self.strategy, self.mockery),
+        )
+    }
+
+    pub fn with_level(self, level: u8) -> Stats {
+        let bonus = cmp::min(level, MAX_LEVEL) / LEVELS_PER_BONUS;
+        Stats {
+            wit: self.wit + bonus,
+            brutality: self.brutality + bonus,
+            strategy: self.strategy + bonus,
+            mockery: self.mockery + bonus,
         }
     }
+
+    // Every stat must be at least 1, since damage is rolled modulo the stat
+    pub fn is_valid(&self) -> bool {
+        [self.wit, self.brutality, self.strategy, self.mockery]
+            .iter()
+            .all(|stat| (1..=MAX_STAT).contains(stat))
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Figure {
+    pub name: String,
+    pub stats: Stats,
+    pub signature_move: SignatureMove,
+    pub enabled: bool,
+    // Bumped on every rebalance, duels record the version they were played under
+    pub version: u32,
+}
+
+pub fn level_for_xp(xp: u32) -> u8 {
+    cmp::min(xp / XP_PER_LEVEL, MAX_LEVEL as u32) as u8
+}
+
+use SignatureMove::*;
+
+// The launch roster, seeded into storage when the contract is initialized
+const ROSTER: [(&str, &str, [u8; 4], SignatureMove); 30] = [
+    ("JuliusCaesar", "Julius Caesar", [5, 6, 6, 3], VeniVidiVici),
+    (
+        "WilliamShakespeare",
+        "William Shakespeare",
+        [9, 2, 5, 4],
+        Flourish,
+    ),
+    ("GenghisKhan", "Genghis Khan", [2, 10, 6, 2], BrutalBurst),
+    (
+        "NapoleonBonaparte",
+        "Napoleon Bonaparte",
+        [4, 4, 9, 3],
+        GrandBattery,
+    ),
+    ("MarkTwain", "Mark Twain", [10, 1, 4, 5], TallTale),
+    ("SunTzu", "Sun Tzu", [5, 2, 10, 3], ReadTheEnemy),
+    ("Socrates", "Socrates", [8, 1, 6, 5], SocraticIrony),
+    ("WinstonChurchill", "Winston Churchill", [6, 3, 5, 6], Rally),
+    (
+        "MarieAntoinette",
+        "Marie Antoinette",
+        [3, 4, 4, 9],
+        Ridicule,
+    ),
+    (
+        "LeonardoDaVinci",
+        "Leonardo da Vinci",
+        [6, 2, 9, 3],
+        Masterpiece,
+    ),
+    ("OscarWilde", "Oscar Wilde", [9, 1, 5, 5], Epigram),
+    ("AttilaTheHun", "Attila the Hun", [1, 10, 5, 4], Scourge),
+    (
+        "TheodoreRoosevelt",
+        "Theodore Roosevelt",
+        [4, 8, 5, 3],
+        BigStick,
+    ),
+    (
+        "BenjaminFranklin",
+        "Benjamin Franklin",
+        [7, 2, 7, 4],
+        Almanac,
+    ),
+    (
+        "HannibalBarca",
+        "Hannibal Barca",
+        [3, 5, 10, 2],
+        Encirclement,
+    ),
+    ("Confucius", "Confucius", [9, 1, 6, 4], Analects),
+    ("VladTheImpaler", "Vlad the Impaler", [2, 9, 5, 4], Impale),
+    (
+        "NiccoloMachiavelli",
+        "Niccolò Machiavelli",
+        [5, 2, 9, 4],
+        Intrigue,
+    ),
+    ("KarlMarx", "Karl Marx", [6, 3, 6, 5], Redistribution),
+    (
+        "FriedrichNietzsche",
+        "Friedrich Nietzsche",
+        [5, 4, 5, 6],
+        WillToPower,
+    ),
+    ("JoanOfArc", "Joan of Arc", [4, 7, 5, 4], HolyBanner),
+    ("AndrewJackson", "Andrew Jackson", [3, 9, 4, 4], OldHickory),
+    (
+        "OttoVonBismarck",
+        "Otto von Bismarck",
+        [4, 5, 8, 3],
+        Realpolitik,
+    ),
+    ("SalvadorDali", "Salvador Dalí", [7, 2, 4, 7], Surrealism),
+    ("HarrietTubman", "Harriet Tubman", [4, 6, 7, 3], Conductor),
+    ("NelsonMandela", "Nelson Mandela", [6, 3, 7, 4], LongWalk),
+    ("JohnFKennedy", "John F. Kennedy", [6, 4, 6, 4], Moonshot),
+    (
+        "MartinLutherKingJr",
+        "Martin Luther King Jr.",
+        [8, 2, 6, 4],
+        Dream,
+    ),
+    ("MalcolmX", "Malcolm X", [7, 4, 5, 4], AnyMeans),
+    (
+        "FrederickDouglass",
+        "Frederick Douglass",
+        [7, 3, 6, 4],
+        Oratory,
+    ),
+];
+
+// The roster keeps the order of the original figure enum, so this maps the enum index that the
+// first contract layout stored to the figure id
+pub fn launch_figure(index: u8) -> FigureId {
+    ROSTER[index as usize].0.to_string()
+}
+
+pub fn default_roster() -> impl Iterator<Item = (FigureId, Figure)> {
+    ROSTER.into_iter().map(
+        |(id, name, [wit, brutality, strategy, mockery], signature_move)| {
+            let figure = Figure {
+                name: name.to_string(),
+                stats: Stats {
+                    wit,
+                    brutality,
+                    strategy,
+                    mockery,
+                },
+                signature_move,
+                enabled: true,
+                version: 1,
+            };
+            (id.to_string(), figure)
+        },
+    )
 }
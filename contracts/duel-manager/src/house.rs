@@ -0,0 +1,204 @@
+use crate::*;
+
+#[near_bindgen]
+impl DuelManagerContract {
+    pub fn get_bankroll(&self) -> Bankroll {
+        self.bankroll.clone()
+    }
+
+    // Duels the contract itself, for when no one else is around. Without a `house_figure`
+    // the house picks one at random.
+    #[payable]
+    pub fn create_house_duel(
+        &mut self,
+        figure: FigureId,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        house_figure: Option<FigureId>,
+        options: Option<DuelOptions>,
+    ) -> U128 {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._create_house_duel(
+            sender,
+            figure,
+            stake,
+            secret_hash,
+            house_figure,
+            options.unwrap_or_default(),
+        )
+    }
+
+    pub fn set_house_limits(&mut self, max_stake: U128, max_exposure: U128) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            max_stake.0 <= max_exposure.0,
+            "Maximum stake cannot exceed the maximum exposure."
+        );
+
+        self.bankroll.max_stake = max_stake;
+        self.bankroll.max_exposure = max_exposure;
+    }
+
+    // Only the part of the bankroll not staked in running duels can be withdrawn
+    pub fn withdraw_bankroll(&mut self, amount: U128) -> Promise {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            self.bankroll.balance.0 >= amount.0,
+            "Insufficient bankroll."
+        );
+
+        self.bankroll.balance.0 -= amount.0;
+        let token = self.ft_contract.clone();
+        self.transfer(&token, sender, amount)
+    }
+
+    pub(crate) fn fund_bankroll(&mut self, sender: AccountId, amount: U128) {
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let balance = self
+            .stakes
+            .entry((sender, self.ft_contract.clone()))
+            .or_insert(0);
+        *balance -= amount.0;
+        self.bankroll.balance.0 += amount.0;
+
+        env::log_str(&format!("Bankroll funded with {}.", amount.0));
+    }
+
+    pub(crate) fn _create_house_duel(
+        &mut self,
+        sender: AccountId,
+        figure: FigureId,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        house_figure: Option<FigureId>,
+        options: DuelOptions,
+    ) -> U128 {
+        assert!(
+            !options.blind_pick && !options.draft && !options.team && !options.require_cards,
+            "House duels cannot use blind picks, drafts, teams or figure cards."
+        );
+        assert!(
+            options.token.is_none(),
+            "The bankroll only covers the default token."
+        );
+        assert!(stake.0 > 0, "House duels must be staked.");
+        assert!(
+            stake.0 <= self.bankroll.max_stake.0,
+            "Stake exceeds the house limit."
+        );
+        assert!(
+            self.bankroll.exposure.0 + stake.0 <= self.bankroll.max_exposure.0,
+            "The house is at its exposure limit."
+        );
+        // The house matches the stake, which is all it stands to lose
+        assert!(
+            self.bankroll.balance.0 >= stake.0,
+            "The bankroll cannot cover this duel."
+        );
+
+        let seed = env::random_seed();
+        let house_figure = house_figure.unwrap_or_else(|| self.random_figure(&figure, &seed));
+        self.assert_figure(&house_figure);
+        assert!(
+            house_figure != figure,
+            "Historical figure already selected."
+        );
+
+        let duel_id = self.open_duel(
+            sender.clone(),
+            Pick::Open(figure),
+            stake,
+            secret_hash,
+            options,
+            None,
+        );
+        let mut duel = self.duels.get(&duel_id.0).unwrap().clone();
+
+        self.bankroll.balance.0 -= stake.0;
+        self.bankroll.exposure.0 += stake.0;
+
+        // The house secret is public, but the player committed to theirs before seeing it
+        duel.house = true;
+        duel.commitment_b = Some(Base64VecU8(env::sha256(&seed)));
+        duel.secret_b = Some(Base64VecU8(seed));
+
+        let previous = duel.status();
+        self.start_duel(&mut duel, env::current_account_id(), house_figure.clone());
+        self.index_duel(&duel, Some(previous));
+        self.duels.insert(duel_id.0, duel);
+
+        env::log_str(&format!(
+            "The house accepted duel {} with {}.",
+            duel_id.0, house_figure
+        ));
+        duel_id
+    }
+
+    // Plays the house's reply as soon as the player has moved
+    pub(crate) fn take_house_turn(&mut self, duel_id: U128) -> PromiseOrValue<u8> {
+        let duel = self.duels.get(&duel_id.0).unwrap();
+        let roll = duel.roll(duel.turns.len(), &env::random_seed());
+        let style = Self::house_style(duel, &roll);
+
+        self._take_turn(env::current_account_id(), duel_id, style, false)
+    }
+
+    // The pot settles against the bankroll. Only the player's share leaves the contract.
+    pub(crate) fn settle_house_stakes(&mut self, duel: &Duel, winner: Winner) -> Option<Promise> {
+        let stake = duel.stake.0;
+        let fee = duel.fee();
+        self.bankroll.exposure.0 -= stake;
+
+        match winner {
+            Winner::PlayerA => {
+                self.bankroll.lost.0 += stake;
+                self.burn(&duel.token, U128(fee));
+                Some(self.transfer(&duel.token, duel.player_a.clone(), U128(duel.pot() - fee)))
+            }
+            Winner::PlayerB => {
+                self.bankroll.balance.0 += duel.pot() - fee;
+                self.bankroll.won.0 += stake - fee;
+                self.burn(&duel.token, U128(fee));
+                None
+            }
+            Winner::Draw => {
+                self.bankroll.balance.0 += stake;
+                Some(self.transfer(&duel.token, duel.player_a.clone(), duel.stake))
+            }
+        }
+    }
+
+    fn random_figure(&self, taken: &str, seed: &[u8]) -> FigureId {
+        let available: Vec<&FigureId> = self
+            .figures
+            .iter()
+            .filter(|(id, figure)| figure.enabled && id.as_str() != taken)
+            .map(|(id, _)| id)
+            .collect();
+        assert!(!available.is_empty(), "Figure is not available.");
+
+        available[seed[0] as usize % available.len()].clone()
+    }
+
+    // Mostly counters the player's last roast, sometimes leans on the house figure's best
+    // stat, and otherwise mixes it up. Uses roll bytes the damage roll doesn't.
+    fn house_style(duel: &Duel, roll: &[u8]) -> RoastStyle {
+        let stats = duel.stats(false, false);
+        match (roll[4] % 4, duel.turns.last()) {
+            (0 | 1, Some(last_turn)) => RoastStyle::counter(last_turn.style),
+            (2, _) => RoastStyle::ALL
+                .into_iter()
+                .max_by_key(|style| style.stat(&stats))
+                .unwrap(),
+            _ => RoastStyle::ALL[roll[5] as usize % RoastStyle::ALL.len()],
+        }
+    }
+}
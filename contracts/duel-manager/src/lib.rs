@@ -1,33 +1,75 @@
 use itertools::Itertools;
-use near_sdk::json_types::U128;
-use near_sdk::store::{IterableMap, LookupMap};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::store::{IterableMap, LookupMap, TreeMap};
 use near_sdk::{
     env, ext_contract, near, near_bindgen, serde_json, AccountId, NearToken, PanicOnDefault,
     Promise, PromiseError, PromiseOrValue,
 };
-use std::cmp;
-use std::collections::HashMap;
-use strum::IntoEnumIterator;
+use std::ops::Bound;
 
+mod betting;
+mod draft;
 mod figures;
+mod house;
+mod matchmaking;
+mod migrate;
+mod royale;
 mod storage;
+mod tournament;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests;
 
 use figures::*;
+use migrate::DuelV0;
 use storage::*;
 
-const MIN_STAKE: u128 = 10u128.pow(24);
-const MAX_TURNS: usize = 10;
+const DEFAULT_RATING: u32 = 1200;
+const RATING_K_FACTOR: i64 = 32;
+// Elo expected scores in thousandths for a rating lead of 0, 25, 50 ... 800 points. Kept as a
+// table so ratings don't depend on floating point.
+const EXPECTED_SCORES: [u32; 33] = [
+    500, 536, 571, 606, 640, 673, 703, 733, 760, 785, 808, 830, 849, 867, 882, 896, 909, 920, 930,
+    939, 947, 954, 960, 965, 969, 973, 977, 980, 983, 985, 987, 989, 990,
+];
+const RATING_STEP: u32 = 25;
+const WIN_XP: u32 = 30;
+const DRAW_XP: u32 = 20;
+const LOSS_XP: u32 = 10;
+const MIN_TURN_TIMEOUT: u64 = 60 * 60 * 1_000_000_000;
+const MAX_TURN_TIMEOUT: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+const MIN_CLOCK: u64 = 60 * 1_000_000_000;
+const MAX_INCREMENT: u64 = 24 * 60 * 60 * 1_000_000_000;
+const ROASTI_DECIMALS: u8 = 24;
+const MAX_DECIMALS: u8 = 32;
+// In whole tokens
+const MAX_MIN_STAKE: u128 = 1_000;
+const MAX_FEE_PERCENT: u8 = 25;
 
 #[near(serializers = [json, borsh])]
 #[derive(Clone)]
 pub struct FigureData {
-    pub name: HistoricalFigure,
+    pub id: FigureId,
+    pub name: String,
+    pub signature_move: SignatureMove,
+    pub version: u32,
+    pub level: u8,
     pub wit: u8,
     pub brutality: u8,
     pub strategy: u8,
     pub mockery: u8,
 }
 
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct FigureProgress {
+    pub figure: FigureId,
+    pub xp: u32,
+    pub level: u8,
+    pub next_level_xp: Option<u32>,
+    pub stats: Stats,
+}
+
 #[near(serializers = [json, borsh])]
 #[derive(Clone)]
 pub struct LeaderboardItem {
@@ -38,14 +80,46 @@ pub struct LeaderboardItem {
 #[near(serializers = [json, borsh])]
 #[derive(Clone)]
 pub struct RoastIndex {
-    pub duel_id: U128,
+    // Exactly one is set. Royale turns are set with `set_royale_roast`.
+    pub duel_id: Option<U128>,
+    pub royale_id: Option<U128>,
     pub turn: usize,
-    pub current_figure: HistoricalFigure,
-    pub next_figure: HistoricalFigure,
+    pub attacker: AccountId,
+    pub defender: AccountId,
+    pub current_figure: FigureId,
+    pub next_figure: FigureId,
+    pub base_stat: u8,
+    pub max_damage: u8,
+    pub roll: u8,
+    pub bonus: u8,
+    pub penalty: u8,
     pub damage: u8,
     pub style: RoastStyle,
+    pub signature: Option<SignatureMove>,
+    pub critical: bool,
+    pub dodged: bool,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct TokenData {
+    pub account_id: AccountId,
+    pub decimals: u8,
+    pub min_stake: U128,
+}
+
+// Returns are in basis points of the bet, so 18,000 means a winning bet pays 1.8x
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Odds {
+    pub pool_a: U128,
+    pub pool_b: U128,
+    pub return_a: Option<u32>,
+    pub return_b: Option<u32>,
+    pub result: Option<BetResult>,
 }
 
+#[allow(dead_code)]
 #[ext_contract(ext_ft_contract)]
 trait FtContract {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
@@ -53,15 +127,67 @@ trait FtContract {
     fn ft_burn(&mut self, amount: U128);
 }
 
+#[allow(dead_code)]
+#[ext_contract(ext_nft_contract)]
+trait NftContract {
+    fn owns_figure(&self, account_id: AccountId, figure: String) -> bool;
+}
+
 #[near(serializers = [json, borsh])]
 #[serde(tag = "function", rename_all = "snake_case")]
 pub enum Msg {
     CreateDuel {
-        figure: HistoricalFigure,
+        figure: Option<FigureId>,
+        figure_hash: Option<Base64VecU8>,
+        secret_hash: Base64VecU8,
+        #[serde(default)]
+        options: DuelOptions,
+        opponent: Option<AccountId>,
     },
     AcceptDuel {
         duel_id: U128,
-        figure: HistoricalFigure,
+        figure: Option<FigureId>,
+        figure_hash: Option<Base64VecU8>,
+        secret_hash: Base64VecU8,
+    },
+    JoinTournament {
+        tournament_id: U128,
+        figure: FigureId,
+    },
+    JoinQueue {
+        figure: FigureId,
+        min_stake: U128,
+        max_stake: U128,
+        min_rating: Option<u32>,
+        max_rating: Option<u32>,
+    },
+    PlaceBet {
+        duel_id: U128,
+        side: Side,
+    },
+    CreateRoyale {
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+        max_players: u8,
+        knockout_percent: Option<u8>,
+    },
+    JoinRoyale {
+        royale_id: U128,
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+    },
+    CreateHouseDuel {
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+        house_figure: Option<FigureId>,
+        #[serde(default)]
+        options: DuelOptions,
+    },
+    FundBankroll,
+    JoinTeam {
+        duel_id: U128,
+        side: Side,
+        figure: FigureId,
     },
 }
 
@@ -69,33 +195,107 @@ pub enum Msg {
 #[derive(PanicOnDefault)]
 pub struct DuelManagerContract {
     admin_id: AccountId,
+    // The default token, $ROASTI. Bankrolls, tournaments and matchmaking only use this one.
     ft_contract: AccountId,
+    tokens: IterableMap<AccountId, Token>,
+    // Receives the fees in tokens other than $ROASTI, which can't be burned
+    treasury_id: AccountId,
+    nft_contract: Option<AccountId>,
+    figures: IterableMap<FigureId, Figure>,
     duels: IterableMap<u128, Duel>,
     next_duel_id: u128,
-    stakes: LookupMap<AccountId, u128>,
-    total_stake: u128,
+    stakes: LookupMap<(AccountId, AccountId), u128>,
+    // Everything the contract owes in each token, including tokens no longer whitelisted
+    total_stakes: IterableMap<AccountId, u128>,
+    min_turns: u8,
+    max_turns: u8,
+    tournaments: IterableMap<u128, Tournament>,
+    next_tournament_id: u128,
+    queue: IterableMap<AccountId, QueueEntry>,
+    ratings: LookupMap<AccountId, u32>,
+    player_stats: LookupMap<AccountId, PlayerStats>,
+    wins_index: TreeMap<(u32, AccountId), ()>,
+    damage_index: TreeMap<(u32, AccountId), ()>,
+    rating_index: TreeMap<(u32, AccountId), ()>,
+    figure_xp: LookupMap<(AccountId, FigureId), u32>,
+    status_index: TreeMap<(DuelStatus, u128), ()>,
+    account_index: TreeMap<(AccountId, u128), ()>,
+    // Open challenges, keyed by the challenged account
+    challenge_index: TreeMap<(AccountId, u128), ()>,
+    bets: LookupMap<u128, BetPool>,
+    account_bets: TreeMap<(AccountId, u128), Bet>,
+    // Ordered by id so royales can be paged, as canceled ones leave gaps
+    royales: TreeMap<u128, Royale>,
+    next_royale_id: u128,
+    bankroll: Bankroll,
+    config: Config,
+    // Duels from the first layout, left under the `d` prefix until they are migrated
+    legacy_duels: Option<IterableMap<u128, DuelV0>>,
 }
 
 #[near_bindgen]
 impl DuelManagerContract {
     #[init]
     pub fn new(admin_id: AccountId, ft_contract: AccountId) -> Self {
+        let mut figures = IterableMap::new(b"f");
+        figures.extend(default_roster());
+        let mut tokens = IterableMap::new(b"k");
+        tokens.insert(ft_contract.clone(), Token::new(ROASTI_DECIMALS));
+        let mut total_stakes = IterableMap::new(b"ts".as_slice());
+        total_stakes.insert(ft_contract.clone(), 0);
+
         Self {
+            treasury_id: admin_id.clone(),
             admin_id,
             ft_contract,
-            duels: IterableMap::new(b"d"),
+            tokens,
+            nft_contract: None,
+            figures,
+            duels: IterableMap::new(b"du".as_slice()),
             next_duel_id: 0,
             stakes: LookupMap::new(b"s"),
-            total_stake: 0,
+            total_stakes,
+            min_turns: 2,
+            max_turns: 20,
+            tournaments: IterableMap::new(b"t"),
+            next_tournament_id: 0,
+            queue: IterableMap::new(b"q"),
+            ratings: LookupMap::new(b"r"),
+            player_stats: LookupMap::new(b"p"),
+            wins_index: TreeMap::new(b"lw".as_slice()),
+            damage_index: TreeMap::new(b"ld".as_slice()),
+            rating_index: TreeMap::new(b"lr".as_slice()),
+            figure_xp: LookupMap::new(b"x"),
+            status_index: TreeMap::new(b"ds".as_slice()),
+            account_index: TreeMap::new(b"da".as_slice()),
+            challenge_index: TreeMap::new(b"dc".as_slice()),
+            bets: LookupMap::new(b"b"),
+            account_bets: TreeMap::new(b"ba".as_slice()),
+            royales: TreeMap::new(b"y".as_slice()),
+            next_royale_id: 0,
+            bankroll: Bankroll::default(),
+            config: Config::default(),
+            legacy_duels: None,
         }
     }
 
-    pub fn get_figures() -> Vec<FigureData> {
-        HistoricalFigure::iter()
-            .map(|figure| {
-                let stats = figure.stats();
+    // Only figures that can currently be picked. With an account, stats include the level
+    // bonuses that account has earned.
+    pub fn get_figures(&self, account_id: Option<AccountId>) -> Vec<FigureData> {
+        self.figures
+            .iter()
+            .filter(|(_, figure)| figure.enabled)
+            .map(|(id, figure)| {
+                let level = account_id
+                    .as_ref()
+                    .map_or(0, |account_id| self.figure_level(account_id, id));
+                let stats = figure.stats.with_level(level);
                 FigureData {
-                    name: figure,
+                    id: id.clone(),
+                    name: figure.name.clone(),
+                    signature_move: figure.signature_move,
+                    version: figure.version,
+                    level,
                     wit: stats.wit,
                     brutality: stats.brutality,
                     strategy: stats.strategy,
@@ -105,116 +305,145 @@ impl DuelManagerContract {
             .collect()
     }
 
+    // Includes retired figures, so old duels can still be displayed
+    pub fn get_figure(&self, figure_id: FigureId) -> Option<Figure> {
+        self.figures.get(&figure_id).cloned()
+    }
+
+    pub fn get_figure_progress(&self, account_id: AccountId, figure: FigureId) -> FigureProgress {
+        let stats = self.figures.get(&figure).expect("Figure not found.").stats;
+        let xp = self
+            .figure_xp
+            .get(&(account_id, figure.clone()))
+            .copied()
+            .unwrap_or(0);
+        let level = level_for_xp(xp);
+        FigureProgress {
+            figure,
+            xp,
+            level,
+            next_level_xp: (level < MAX_LEVEL).then(|| (level as u32 + 1) * XP_PER_LEVEL),
+            stats: stats.with_level(level),
+        }
+    }
+
     pub fn get_duel(&self, duel_id: U128) -> Option<Duel> {
         self.duels.get(&duel_id.0).cloned()
     }
 
-    pub fn get_finished_duels(&self, count: usize, offset: usize) -> Vec<Duel> {
-        self.duels
-            .values()
-            .filter(|d| d.winner.is_some())
-            .sorted_by(|a, b| a.creation_time.cmp(&b.creation_time).reverse())
-            .skip(offset)
-            .take(count)
-            .cloned()
-            .collect()
+    // Time left on each side's clock right now, for duels with a time control
+    pub fn get_clocks(&self, duel_id: U128) -> Option<(u64, u64)> {
+        self.duels.get(&duel_id.0)?.live_clocks()
     }
 
-    pub fn get_active_duels(&self, count: usize, offset: usize) -> Vec<Duel> {
-        self.duels
-            .values()
-            .filter(|d| d.player_b.is_some() && d.winner.is_none())
-            .sorted_by(|a, b| a.creation_time.cmp(&b.creation_time).reverse())
-            .skip(offset)
-            .take(count)
-            .cloned()
-            .collect()
+    // Listings are newest first. Pass the last duel id of a page as `from_id` to get the next one.
+    pub fn get_finished_duels(&self, from_id: Option<U128>, count: usize) -> Vec<Duel> {
+        self.duels_by_status(DuelStatus::Finished, from_id, count)
     }
 
-    pub fn get_pending_duels(&self, count: usize, offset: usize) -> Vec<Duel> {
-        self.duels
-            .values()
-            .filter(|d| d.player_b.is_none() && d.winner.is_none())
-            .sorted_by(|a, b| a.creation_time.cmp(&b.creation_time).reverse())
-            .skip(offset)
-            .take(count)
-            .cloned()
-            .collect()
+    pub fn get_active_duels(&self, from_id: Option<U128>, count: usize) -> Vec<Duel> {
+        self.duels_by_status(DuelStatus::Active, from_id, count)
     }
 
-    pub fn get_account_duels(&self, account_id: AccountId) -> Vec<Duel> {
-        self.duels
-            .values()
-            .filter(|d| d.player_a == account_id || d.player_b.as_ref() == Some(&account_id))
-            .sorted_by(|a, b| a.creation_time.cmp(&b.creation_time).reverse())
-            .cloned()
-            .collect()
+    pub fn get_pending_duels(&self, from_id: Option<U128>, count: usize) -> Vec<Duel> {
+        self.duels_by_status(DuelStatus::Pending, from_id, count)
     }
 
-    pub fn get_leaderboard_by_wins(&self, count: usize, offset: usize) -> Vec<LeaderboardItem> {
-        let mut win_board: HashMap<AccountId, u32> = HashMap::new();
+    pub fn get_incoming_challenges(
+        &self,
+        account_id: AccountId,
+        from_id: Option<U128>,
+        count: usize,
+    ) -> Vec<Duel> {
+        let end = match from_id {
+            Some(from_id) => Bound::Excluded((account_id.clone(), from_id.0)),
+            None => Bound::Included((account_id.clone(), u128::MAX)),
+        };
 
-        for duel in self.duels.values() {
-            match duel.winner {
-                Some(Winner::PlayerA) => *win_board.entry(duel.player_a.clone()).or_insert(0) += 1,
-                Some(Winner::PlayerB) => {
-                    *win_board.entry(duel.player_b.clone().unwrap()).or_insert(0) += 1
-                }
-                _ => (),
-            }
-        }
+        self.challenge_index
+            .range((Bound::Included((account_id.clone(), 0)), end))
+            .rev()
+            .take(count)
+            .map(|((_, duel_id), _)| self.duels.get(duel_id).unwrap().clone())
+            .collect()
+    }
 
-        win_board
-            .into_iter()
-            .sorted_by(|a, b| a.1.cmp(&b.1).reverse())
-            .skip(offset)
+    pub fn get_account_duels(
+        &self,
+        account_id: AccountId,
+        from_id: Option<U128>,
+        count: usize,
+    ) -> Vec<Duel> {
+        self.account_duel_ids(&account_id, from_id)
             .take(count)
-            .map(|(account_id, value)| LeaderboardItem { account_id, value })
+            .map(|duel_id| self.duels.get(&duel_id).unwrap().clone())
             .collect()
     }
 
-    pub fn get_leaderboard_by_damage(&self, count: usize, offset: usize) -> Vec<LeaderboardItem> {
-        let mut damage_board: HashMap<AccountId, u32> = HashMap::new();
+    pub fn get_player_stats(&self, account_id: AccountId) -> PlayerStats {
+        self.player_stats
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
 
-        for duel in self.duels.values() {
-            if duel.winner.is_none() {
-                continue;
-            }
+    pub fn get_rating(&self, account_id: AccountId) -> u32 {
+        self.ratings
+            .get(&account_id)
+            .copied()
+            .unwrap_or(DEFAULT_RATING)
+    }
 
-            for (i, turn) in duel.turns.iter().enumerate() {
-                let attacker = if i % 2 == 0 {
-                    duel.player_a.clone()
-                } else {
-                    duel.player_b.clone().unwrap()
-                };
+    // Leaderboards are highest first. Pass the last account of a page as `from_account` to get
+    // the next one.
+    pub fn get_leaderboard_by_wins(
+        &self,
+        from_account: Option<AccountId>,
+        count: usize,
+    ) -> Vec<LeaderboardItem> {
+        let from = from_account
+            .map(|account_id| (self.get_player_stats(account_id.clone()).wins, account_id));
+        Self::leaderboard(&self.wins_index, from, count)
+    }
 
-                *damage_board.entry(attacker).or_insert(0) += turn.damage as u32;
-            }
-        }
+    pub fn get_leaderboard_by_rating(
+        &self,
+        from_account: Option<AccountId>,
+        count: usize,
+    ) -> Vec<LeaderboardItem> {
+        let from = from_account.map(|account_id| (self.get_rating(account_id.clone()), account_id));
+        Self::leaderboard(&self.rating_index, from, count)
+    }
 
-        damage_board
-            .into_iter()
-            .sorted_by(|a, b| a.1.cmp(&b.1).reverse())
-            .skip(offset)
-            .take(count)
-            .map(|(account_id, value)| LeaderboardItem { account_id, value })
-            .collect()
+    pub fn get_leaderboard_by_damage(
+        &self,
+        from_account: Option<AccountId>,
+        count: usize,
+    ) -> Vec<LeaderboardItem> {
+        let from = from_account.map(|account_id| {
+            let damage = self.get_player_stats(account_id.clone()).damage_dealt;
+            (damage, account_id)
+        });
+        Self::leaderboard(&self.damage_index, from, count)
+    }
+
+    pub fn get_config(&self) -> Config {
+        self.config.clone()
     }
 
     pub fn get_top_duel(&self) -> Option<Duel> {
         let now = env::block_timestamp();
-        let one_day = 24 * 60 * 60 * 1_000_000_000;
+        let window = self.config.top_duel_window;
 
         self.duels
             .values()
             .filter(|d| {
-                let has_winning_player = match d.winner {
-                    Some(Winner::PlayerA) | Some(Winner::PlayerB) => true,
-                    _ => false,
-                };
+                let has_winning_player =
+                    matches!(d.winner, Some(Winner::PlayerA) | Some(Winner::PlayerB));
                 has_winning_player
-                    && d.turns.len() == 10
-                    && now.saturating_sub(d.turns.last().unwrap().creation_time) <= one_day
+                    && d.turns
+                        .last()
+                        .is_some_and(|turn| now.saturating_sub(turn.creation_time) <= window)
             })
             .max_by(|a, b| a.stake.0.cmp(&b.stake.0))
             .cloned()
@@ -223,56 +452,312 @@ impl DuelManagerContract {
     pub fn get_roast_queue(&self) -> Vec<RoastIndex> {
         self.duels
             .values()
-            .map(|duel| {
+            .flat_map(|duel| {
                 duel.turns
                     .iter()
                     .enumerate()
                     .filter(|(_, turn)| turn.roast_cid.is_none())
                     .map(|(i, turn)| RoastIndex {
-                        duel_id: duel.id,
+                        duel_id: Some(duel.id),
+                        royale_id: None,
                         turn: i,
-                        current_figure: if i % 2 == 0 {
-                            duel.figure_a.clone()
-                        } else {
-                            duel.figure_b.unwrap()
-                        },
-                        next_figure: if i % 2 == 0 {
-                            duel.figure_b.unwrap()
-                        } else {
-                            duel.figure_a.clone()
-                        },
+                        attacker: turn.attacker.clone(),
+                        defender: turn.defender.clone(),
+                        current_figure: turn.figure.clone(),
+                        next_figure: duel
+                            .member(!duel.is_player_a_turn(i), duel.is_partner_turn(i))
+                            .1
+                            .clone(),
+                        base_stat: turn.base_stat,
+                        max_damage: turn.max_damage,
+                        roll: turn.roll,
+                        bonus: turn.bonus,
+                        penalty: turn.penalty,
                         damage: turn.damage,
                         style: turn.style,
+                        signature: turn.signature,
+                        critical: turn.critical,
+                        dodged: turn.dodged,
                     })
             })
-            .flatten()
+            .chain(self.royales.values().flat_map(|royale| {
+                royale
+                    .turns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, turn)| turn.roast_cid.is_none())
+                    .map(|(i, turn)| RoastIndex {
+                        duel_id: None,
+                        royale_id: Some(royale.id),
+                        turn: i,
+                        attacker: turn.attacker.clone(),
+                        defender: turn.target.clone(),
+                        current_figure: turn.figure.clone(),
+                        next_figure: turn.target_figure.clone(),
+                        base_stat: turn.base_stat,
+                        max_damage: turn.max_damage,
+                        roll: turn.roll,
+                        bonus: turn.bonus,
+                        penalty: turn.penalty,
+                        damage: turn.damage,
+                        style: turn.style,
+                        signature: None,
+                        critical: turn.critical,
+                        dodged: turn.dodged,
+                    })
+            }))
+            .collect()
+    }
+
+    pub fn get_balance(&self, account_id: AccountId, token: Option<AccountId>) -> U128 {
+        let token = token.unwrap_or_else(|| self.ft_contract.clone());
+        U128(self.stakes.get(&(account_id, token)).copied().unwrap_or(0))
+    }
+
+    pub fn get_tokens(&self) -> Vec<TokenData> {
+        self.tokens
+            .iter()
+            .map(|(account_id, token)| TokenData {
+                account_id: account_id.clone(),
+                decimals: token.decimals,
+                min_stake: token.min_stake,
+            })
+            .collect()
+    }
+
+    pub fn get_turn_bounds(&self) -> (u8, u8) {
+        (self.min_turns, self.max_turns)
+    }
+
+    pub fn verify_rolls(&self, duel_id: U128) -> Vec<bool> {
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.");
+        // Migrated duels were rolled from the block seed alone and can't be replayed
+        if duel.secret_a.is_none() {
+            return Vec::new();
+        }
+
+        duel.turns
+            .iter()
+            .enumerate()
+            .map(|(i, turn)| {
+                let roll = duel.roll(i, &turn.seed.0);
+                let hit = duel.damage(i, turn.style, turn.signature, &roll);
+                hit.roll == turn.roll
+                    && hit.bonus == turn.bonus
+                    && hit.penalty == turn.penalty
+                    && hit.damage == turn.damage
+                    && hit.critical == turn.critical
+                    && hit.dodged == turn.dodged
+            })
             .collect()
     }
 
-    pub fn get_balance(&self, account_id: AccountId) -> U128 {
-        U128(self.stakes.get(&account_id).copied().unwrap_or(0))
+    // Blind pick duels take a `figure_hash` of sha256(len || figure id || salt) instead of a figure
+    #[payable]
+    pub fn create_duel(
+        &mut self,
+        figure: Option<FigureId>,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        options: Option<DuelOptions>,
+        opponent: Option<AccountId>,
+        figure_hash: Option<Base64VecU8>,
+    ) -> PromiseOrValue<U128> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._create_duel(
+            sender,
+            Pick::new(figure, figure_hash),
+            stake,
+            secret_hash,
+            options.unwrap_or_default(),
+            opponent,
+        )
+    }
+
+    #[payable]
+    pub fn accept_duel(
+        &mut self,
+        duel_id: U128,
+        figure: Option<FigureId>,
+        secret_hash: Base64VecU8,
+        figure_hash: Option<Base64VecU8>,
+    ) -> Option<Promise> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let pick = Pick::new(figure, figure_hash);
+        self._accept_duel(sender, duel_id, pick, secret_hash)
+    }
+
+    // Joins a team duel as the second player on a side. Pays the same stake as the leads.
+    #[payable]
+    pub fn join_team(&mut self, duel_id: U128, side: Side, figure: FigureId) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._join_team(sender, duel_id, side, figure);
+    }
+
+    #[payable]
+    pub fn decline_duel(&mut self, duel_id: U128) -> Promise {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.");
+
+        assert!(
+            duel.opponent.as_ref() == Some(&sender),
+            "Only the challenged account can decline."
+        );
+        assert!(duel.player_b.is_none(), "Duel already accepted.");
+
+        let duel = duel.clone();
+        let promise = self.refund_players(&duel);
+        self.remove_duel(duel_id.0);
+
+        env::log_str(&format!(
+            "Duel {} declined by {}. Stakes refunded.",
+            duel_id.0, sender
+        ));
+        promise
+    }
+
+    #[payable]
+    pub fn commit_secret(&mut self, duel_id: U128, secret_hash: Base64VecU8) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+        assert!(secret_hash.0.len() == 32, "Secret hash must be 32 bytes.");
+
+        let sender = env::predecessor_account_id();
+        let duel = self.duels.get_mut(&duel_id.0).expect("Duel not found.");
+
+        // Duels started by the contract, such as tournament brackets, have no commitments yet
+        let commitment = if sender == duel.player_a {
+            &mut duel.commitment_a
+        } else if duel.player_b.as_ref() == Some(&sender) {
+            &mut duel.commitment_b
+        } else {
+            env::panic_str("Sender must be player.");
+        };
+        assert!(commitment.is_none(), "Secret already committed.");
+        *commitment = Some(secret_hash);
+        if duel.deadline.is_some() {
+            duel.extend_deadline();
+        }
     }
 
     #[payable]
-    pub fn create_duel(&mut self, figure: HistoricalFigure, stake: U128) -> U128 {
+    pub fn reveal_secret(&mut self, duel_id: U128, secret: Base64VecU8) {
         assert!(
             env::attached_deposit() == NearToken::from_yoctonear(1),
             "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
         );
 
         let sender = env::predecessor_account_id();
-        self._create_duel(sender, figure, stake)
+        let duel = self.duels.get_mut(&duel_id.0).expect("Duel not found.");
+
+        assert!(
+            duel.player_b.is_some(),
+            "Duel must be accepted before revealing secrets."
+        );
+        assert!(
+            duel.is_committed(),
+            "Both players must commit before revealing secrets."
+        );
+        assert!(secret.0.len() == 32, "Secret must be 32 bytes.");
+
+        let hash = Base64VecU8(env::sha256(&secret.0));
+        if sender == duel.player_a {
+            assert!(duel.secret_a.is_none(), "Secret already revealed.");
+            assert!(
+                Some(&hash) == duel.commitment_a.as_ref(),
+                "Secret does not match commitment."
+            );
+            duel.secret_a = Some(secret);
+        } else if duel.player_b.as_ref() == Some(&sender) {
+            assert!(duel.secret_b.is_none(), "Secret already revealed.");
+            assert!(
+                Some(&hash) == duel.commitment_b.as_ref(),
+                "Secret does not match commitment."
+            );
+            duel.secret_b = Some(secret);
+        } else {
+            env::panic_str("Sender must be player.");
+        }
+        if duel.deadline.is_some() {
+            duel.extend_deadline();
+        }
+        duel.start_clock();
+
+        env::log_str(&format!(
+            "Secret revealed by {} for duel {}.",
+            sender, duel_id.0
+        ));
     }
 
+    // Blind picks are revealed only once the duel is accepted. Both players may have picked
+    // the same figure, since neither could see the other's choice.
     #[payable]
-    pub fn accept_duel(&mut self, duel_id: U128, figure: HistoricalFigure) {
+    pub fn reveal_figure(&mut self, duel_id: U128, figure: FigureId, salt: Base64VecU8) {
         assert!(
             env::attached_deposit() == NearToken::from_yoctonear(1),
             "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
         );
 
         let sender = env::predecessor_account_id();
-        self._accept_duel(sender, duel_id, figure);
+        let mut duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+
+        assert!(duel.blind_pick, "Duel does not use blind picks.");
+        assert!(
+            duel.player_b.is_some(),
+            "Duel must be accepted before revealing figures."
+        );
+        assert!(duel.winner.is_none(), "Duel already completed.");
+        self.assert_figure(&figure);
+        assert!(salt.0.len() == 32, "Salt must be 32 bytes.");
+
+        // The length prefix keeps the id and the salt from running together
+        let length = (figure.len() as u32).to_le_bytes();
+        let hash = Base64VecU8(env::sha256(&[&length, figure.as_bytes(), &salt.0].concat()));
+        let (revealed, commitment) = if sender == duel.player_a {
+            (&mut duel.figure_a, &duel.figure_commitment_a)
+        } else if duel.player_b.as_ref() == Some(&sender) {
+            (&mut duel.figure_b, &duel.figure_commitment_b)
+        } else {
+            env::panic_str("Sender must be player.");
+        };
+        assert!(revealed.is_none(), "Figure already revealed.");
+        assert!(
+            Some(&hash) == commitment.as_ref(),
+            "Figure does not match commitment."
+        );
+        *revealed = Some(figure.clone());
+
+        if duel.figures_revealed() {
+            self.load_figures(&mut duel);
+        }
+        self.duels.insert(duel_id.0, duel);
+
+        env::log_str(&format!(
+            "{} revealed {} for duel {}.",
+            sender, figure, duel_id.0
+        ));
     }
 
     #[payable]
@@ -283,6 +768,28 @@ impl DuelManagerContract {
         );
 
         let sender = env::predecessor_account_id();
+        self._take_turn(sender, duel_id, style, false)
+    }
+
+    // Same as a regular turn, but with the figure's signature move. Once per player per duel.
+    #[payable]
+    pub fn take_signature_turn(&mut self, duel_id: U128, style: RoastStyle) -> PromiseOrValue<u8> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._take_turn(sender, duel_id, style, true)
+    }
+
+    fn _take_turn(
+        &mut self,
+        sender: AccountId,
+        duel_id: U128,
+        style: RoastStyle,
+        signature: bool,
+    ) -> PromiseOrValue<u8> {
         let mut duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
 
         assert!(
@@ -290,105 +797,101 @@ impl DuelManagerContract {
             "Duel must be accepted before taking turns."
         );
         assert!(
-            duel.turns.len() < MAX_TURNS && duel.winner.is_none(),
-            "Duel already completed."
+            duel.is_full(),
+            "Team duels need all four players before taking turns."
+        );
+        assert!(
+            duel.is_revealed(),
+            "Both secrets must be revealed before taking turns."
+        );
+        assert!(
+            duel.figures_revealed(),
+            "Both figures must be chosen before taking turns."
         );
+        assert!(duel.winner.is_none(), "Duel already completed.");
 
         // Determine whose turn it is
-        let is_player_a = duel.turns.len() % 2 == 0;
-        let current_player = if is_player_a {
-            &duel.player_a
-        } else {
-            duel.player_b.as_ref().unwrap()
-        };
+        let is_player_a = duel.is_player_a_turn(duel.turns.len());
+        let partner = duel.is_partner_turn(duel.turns.len());
+        let (current_player, figure) = duel.member(is_player_a, partner);
 
         assert!(current_player == &sender, "Invalid sender.");
 
-        let last_roast_style = duel.turns.iter().last().map(|turn| turn.style);
-
-        // Roll damage based on the chosen roast style
-        let figure = if is_player_a {
-            duel.figure_a.clone()
+        let figure = figure.clone();
+        let defender = duel.member(!is_player_a, partner).0.clone();
+        duel.punch_clock(is_player_a);
+        let signature = if signature {
+            assert!(
+                !duel.has_used_signature(&sender),
+                "Signature move already used."
+            );
+            Some(self.figures.get(&figure).unwrap().signature_move)
         } else {
-            duel.figure_b.clone().unwrap()
-        };
-        let stats = figure.stats();
-        let stat_damage = match style {
-            RoastStyle::Witty => stats.wit,
-            RoastStyle::Brutal => stats.brutality,
-            RoastStyle::Strategic => stats.strategy,
-            RoastStyle::Mocking => stats.mockery,
-        };
-        let max_damage = match last_roast_style {
-            Some(last_style) if style.is_strong_against(last_style) => stat_damage + 5,
-            _ => stat_damage,
+            None
         };
 
-        let damage = (env::random_seed()[0] as u8 % max_damage) + 1; // Roll between 1 and max damage
-        let damage = match last_roast_style {
-            Some(last_style) if style.is_weak_against(last_style) => {
-                cmp::max(damage.checked_sub(5).unwrap_or(0), 1)
-            }
-            _ => damage,
-        };
+        let seed = env::random_seed();
+        let roll = duel.roll(duel.turns.len(), &seed);
+        let hit = duel.damage(duel.turns.len(), style, signature, &roll);
+        let damage = hit.damage;
         duel.turns.push(Turn {
             creation_time: env::block_timestamp(),
+            attacker: sender,
+            defender,
+            figure,
+            base_stat: hit.base_stat,
+            max_damage: hit.max_damage,
+            roll: hit.roll,
+            bonus: hit.bonus,
+            penalty: hit.penalty,
             damage,
             style,
+            signature,
+            critical: hit.critical,
+            dodged: hit.dodged,
+            round: duel.round_results.len() as u8,
+            seed: Base64VecU8(seed),
             roast_cid: None,
         });
+        duel.extend_deadline();
 
-        // If max turns reached, determine winner
-        if duel.turns.len() == MAX_TURNS {
-            let damage_a: u8 = duel.turns.iter().step_by(2).map(|t| t.damage).sum();
-            let damage_b: u8 = duel.turns.iter().skip(1).step_by(2).map(|t| t.damage).sum();
-            if damage_a > damage_b {
-                let player_a = duel.player_a.clone();
-                let reward = duel.stake.0 * 2;
-                let fee = reward / 10;
-                duel.winner = Some(Winner::PlayerA);
-                env::log_str(&format!(
-                    "Duel {} finished! Result: {} won!",
-                    duel.id.0, player_a
-                ));
-
-                self.burn(U128(fee));
-                let promise = self.transfer(player_a.clone(), U128(reward - fee));
-
-                self.duels.insert(duel_id.0, duel);
-                return PromiseOrValue::Promise(promise);
-            } else if damage_a < damage_b {
-                let player_b = duel.player_b.clone().unwrap();
-                let reward = duel.stake.0 * 2;
-                let fee = reward / 10;
-                duel.winner = Some(Winner::PlayerB);
-                env::log_str(&format!(
-                    "Duel {} finished! Result: {} won!",
-                    duel.id.0, player_b
-                ));
-
-                self.burn(U128(fee));
-                let promise = self.transfer(player_b.clone(), U128(reward - fee));
-
-                self.duels.insert(duel_id.0, duel);
-                return PromiseOrValue::Promise(promise);
-            } else {
-                let player_a = duel.player_a.clone();
-                let player_b = duel.player_b.clone().unwrap();
-                let stake = duel.stake;
-                duel.winner = Some(Winner::Draw);
-                env::log_str(&format!("Duel {} finished! Result: draw!", duel.id.0));
+        let defender_hp = if is_player_a {
+            &mut duel.hp_b
+        } else {
+            &mut duel.hp_a
+        };
+        if let Some(hp) = defender_hp {
+            *hp = hp.saturating_sub(damage);
+        }
 
-                self.transfer(player_a, stake);
-                let promise = self.transfer(player_b, stake);
+        // If a figure is knocked out or the round's turns are used up, score the round
+        if let Some(round_winner) = duel.round_outcome() {
+            duel.round_results.push(round_winner);
 
-                self.duels.insert(duel_id.0, duel);
-                return PromiseOrValue::Promise(promise);
+            // Settle only once the match is decided
+            if let Some(winner) = duel.match_outcome() {
+                return match self.settle_duel(duel, winner, WinReason::Decision) {
+                    Some(promise) => PromiseOrValue::Promise(promise),
+                    None => PromiseOrValue::Value(damage),
+                };
             }
+
+            duel.reset_hit_points();
+            env::log_str(&format!(
+                "Duel {} round {} finished.",
+                duel.id.0,
+                duel.round_results.len()
+            ));
         }
 
+        let house_next = duel.house && !duel.is_player_a_turn(duel.turns.len());
         self.duels.insert(duel_id.0, duel);
 
+        if house_next {
+            if let PromiseOrValue::Promise(promise) = self.take_house_turn(duel_id) {
+                return PromiseOrValue::Promise(promise);
+            }
+        }
         PromiseOrValue::Value(damage)
     }
 
@@ -402,70 +905,356 @@ impl DuelManagerContract {
         let sender = env::predecessor_account_id();
         let current_time = env::block_timestamp();
         let duel = self.duels.get(&duel_id.0).expect("Duel not found");
+        assert!(
+            duel.tournament_id.is_none(),
+            "Tournament duels cannot be canceled."
+        );
+
+        let duel = duel.clone();
 
-        if duel.player_b.is_none() {
-            assert!(sender == duel.player_a, "Sender must be player.");
+        // Team duels can be joined by several players before they begin, and any of them can
+        // call off one that never filled up
+        if duel.start_time.is_none() {
+            assert!(
+                duel.players().any(|p| *p == sender),
+                "Sender must be player."
+            );
             assert!(
-                current_time >= duel.creation_time + (24 * 60 * 60 * 1_000_000_000),
-                "You must wait 24 hours to cancel an unaccepted duel."
+                current_time >= duel.creation_time + duel.accept_timeout,
+                "You must wait for the acceptance window to pass to cancel an unaccepted duel."
             );
 
-            let player_a = duel.player_a.clone();
-            let promise = self.transfer(player_a.clone(), duel.stake);
-            self.duels.remove(&duel_id.0);
+            let promise = self.refund_players(&duel);
+            self.remove_duel(duel_id.0);
 
-            env::log_str(&format!(
-                "Duel {} canceled. Stake refunded to {}.",
-                duel_id.0, player_a
-            ));
+            env::log_str(&format!("Duel {} canceled. Stakes refunded.", duel_id.0));
             return promise;
         }
 
-        let is_player_a_turn = duel.turns.len() % 2 == 0;
-        let opponent = if is_player_a_turn {
-            duel.player_b.as_ref().unwrap()
-        } else {
-            &duel.player_a
-        };
-
         assert!(
-            sender == *opponent,
-            "Only the opponent can cancel if no move is made."
+            duel.players().any(|p| *p == sender),
+            "Sender must be player."
         );
 
-        let last_turn_time = duel
-            .turns
-            .iter()
-            .last()
-            .map(|turn| turn.creation_time)
-            .unwrap_or(duel.start_time.expect("Duel has not begun."));
+        // Only a duel where both sides stopped at the same step can be called off. If one side
+        // is left waiting, it is owed a win instead, see `claim_timeout_win`.
+        let stalled = match duel.deadline {
+            Some(deadline) => {
+                assert!(
+                    current_time >= deadline,
+                    "You must wait for the deadline before canceling an ongoing duel."
+                );
+                duel.waiting_on().is_none()
+            }
+            None => {
+                assert!(
+                    current_time >= duel.start_time.unwrap() + duel.reveal_timeout,
+                    "You must wait for the reveal window to pass before canceling an ongoing duel."
+                );
+                duel.draft_step().is_none() && duel.figure_a.is_none() && duel.figure_b.is_none()
+            }
+        };
         assert!(
-            current_time >= last_turn_time + (48 * 60 * 60 * 1_000_000_000),
-            "You must wait 48 hours since the last move before canceling an ongoing duel."
+            stalled,
+            "Ongoing duels cannot be canceled. Claim a timeout win instead."
         );
 
-        let player_a = duel.player_a.clone();
-        let player_b = duel.player_b.clone().unwrap();
-        let stake = duel.stake;
-        let promise = if is_player_a_turn {
-            self.transfer(player_a, stake);
-            self.transfer(player_b, stake)
-        } else {
-            self.transfer(player_b, stake);
-            self.transfer(player_a, stake)
-        };
+        let promise = self.refund_players(&duel);
 
-        self.duels.remove(&duel_id.0);
+        self.refund_bets(duel_id.0);
+        self.remove_duel(duel_id.0);
         env::log_str(&format!("Duel {} canceled due to inactivity.", duel_id.0));
 
         promise
     }
 
-    pub fn set_roast(&mut self, duel_id: U128, turn: usize, roast_cid: String) {
+    // A side wins if the opponent misses a draft step, a secret or a turn, or doesn't reveal
+    // a blind pick in time after they revealed theirs
+    #[payable]
+    pub fn claim_timeout_win(&mut self, duel_id: U128) -> Option<Promise> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
         let sender = env::predecessor_account_id();
-        assert!(sender == self.admin_id, "Sender must be admin.");
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
 
-        let duel = self.duels.get_mut(&duel_id.0).expect("Duel not found.");
+        assert!(duel.winner.is_none(), "Duel already completed.");
+        // The admin claims on behalf of the house
+        let claimant = if duel.house && sender == self.admin_id {
+            env::current_account_id()
+        } else {
+            sender
+        };
+        let is_player_a = if duel.side(true).contains(&&claimant) {
+            true
+        } else if duel.side(false).contains(&&claimant) {
+            false
+        } else {
+            env::panic_str("Sender must be player.");
+        };
+
+        let (overdue, reason) = match (duel.draft_step(), duel.deadline) {
+            (Some((_, drafting_a)), _) => {
+                assert!(
+                    env::block_timestamp() >= duel.draft_deadline.unwrap(),
+                    "The current draft step has not timed out."
+                );
+                (drafting_a != is_player_a, WinReason::Forfeit)
+            }
+            (None, Some(_)) if duel.flag_fallen() == Some(!is_player_a) => {
+                (true, WinReason::FlagFall)
+            }
+            (None, Some(deadline)) => {
+                assert!(
+                    env::block_timestamp() >= deadline,
+                    "The opponent still has time to move."
+                );
+                (duel.waiting_on() == Some(!is_player_a), WinReason::Timeout)
+            }
+            (None, None) => {
+                let start_time = duel.start_time.expect("Duel has not begun.");
+                assert!(
+                    env::block_timestamp() >= start_time + duel.reveal_timeout,
+                    "The opponent still has time to reveal."
+                );
+                let (own, other) = if is_player_a {
+                    (&duel.figure_a, &duel.figure_b)
+                } else {
+                    (&duel.figure_b, &duel.figure_a)
+                };
+                (own.is_some() && other.is_none(), WinReason::Forfeit)
+            }
+        };
+        assert!(overdue, "Opponent is not overdue.");
+
+        let winner = if is_player_a {
+            Winner::PlayerA
+        } else {
+            Winner::PlayerB
+        };
+        env::log_str(&format!(
+            "Duel {} forfeited for missing a deadline.",
+            duel_id.0
+        ));
+        self.settle_duel(duel, winner, reason)
+    }
+
+    // Concedes the duel for the sender's whole side
+    #[payable]
+    pub fn surrender(&mut self, duel_id: U128) -> Option<Promise> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+
+        assert!(duel.start_time.is_some(), "Duel has not begun.");
+        assert!(duel.winner.is_none(), "Duel already completed.");
+        let winner = if duel.side(true).contains(&&sender) {
+            Winner::PlayerB
+        } else if duel.side(false).contains(&&sender) {
+            Winner::PlayerA
+        } else {
+            env::panic_str("Sender must be player.");
+        };
+
+        env::log_str(&format!("{} surrendered duel {}.", sender, duel_id.0));
+        self.settle_duel(duel, winner, WinReason::Surrender)
+    }
+
+    pub fn set_turn_bounds(&mut self, min_turns: u8, max_turns: u8) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            min_turns >= 2 && min_turns <= max_turns,
+            "Invalid turn bounds."
+        );
+        assert!(
+            min_turns % 2 == 0 && max_turns % 2 == 0,
+            "Turn bounds must be even."
+        );
+
+        self.min_turns = min_turns;
+        self.max_turns = max_turns;
+    }
+
+    // In the token's own units. The `token` defaults to $ROASTI.
+    pub fn set_min_stake(&mut self, min_stake: U128, token: Option<AccountId>) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let token = token.unwrap_or_else(|| self.ft_contract.clone());
+        let token = self
+            .tokens
+            .get_mut(&token)
+            .expect("The token is not supported");
+        assert!(
+            min_stake.0 > 0 && min_stake.0 <= MAX_MIN_STAKE * 10u128.pow(token.decimals as u32),
+            "Minimum stake must be positive and at most 1000 tokens."
+        );
+
+        token.min_stake = min_stake;
+    }
+
+    pub fn set_fee_percent(&mut self, fee_percent: u8) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            fee_percent <= MAX_FEE_PERCENT,
+            "Fee can be at most {}%.",
+            MAX_FEE_PERCENT
+        );
+
+        self.config.fee_percent = fee_percent;
+    }
+
+    pub fn set_timeouts(&mut self, accept_timeout: u64, turn_timeout: u64) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            (MIN_TURN_TIMEOUT..=MAX_TURN_TIMEOUT).contains(&accept_timeout),
+            "Acceptance window must be between 1 hour and 7 days."
+        );
+        Self::assert_turn_timeout(turn_timeout);
+
+        self.config.accept_timeout = accept_timeout;
+        self.config.turn_timeout = turn_timeout;
+    }
+
+    pub fn set_top_duel_window(&mut self, top_duel_window: u64) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            (MIN_TURN_TIMEOUT..=MAX_TURN_TIMEOUT).contains(&top_duel_window),
+            "Top duel window must be between 1 hour and 7 days."
+        );
+
+        self.config.top_duel_window = top_duel_window;
+    }
+
+    pub fn set_pick_timeouts(&mut self, reveal_timeout: u64, draft_timeout: u64) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            (MIN_TURN_TIMEOUT..=MAX_TURN_TIMEOUT).contains(&reveal_timeout),
+            "Reveal window must be between 1 hour and 7 days."
+        );
+        assert!(
+            (MIN_TURN_TIMEOUT..=MAX_TURN_TIMEOUT).contains(&draft_timeout),
+            "Draft step window must be between 1 hour and 7 days."
+        );
+
+        self.config.reveal_timeout = reveal_timeout;
+        self.config.draft_timeout = draft_timeout;
+    }
+
+    pub fn add_token(&mut self, token: AccountId, decimals: u8) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            !self.tokens.contains_key(&token),
+            "The token is already supported."
+        );
+        assert!(
+            decimals <= MAX_DECIMALS,
+            "Token decimals must be at most {}.",
+            MAX_DECIMALS
+        );
+
+        self.tokens.insert(token, Token::new(decimals));
+    }
+
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        self.treasury_id = treasury_id;
+    }
+
+    // Stops new deposits in the token. Existing balances and games settle as usual.
+    pub fn remove_token(&mut self, token: AccountId) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            token != self.ft_contract,
+            "The default token cannot be removed."
+        );
+
+        self.tokens.remove(&token);
+    }
+
+    // Duels can only require figure cards once a card contract is set
+    pub fn set_nft_contract(&mut self, nft_contract: Option<AccountId>) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        self.nft_contract = nft_contract;
+    }
+
+    pub fn add_figure(
+        &mut self,
+        figure_id: FigureId,
+        name: String,
+        stats: Stats,
+        signature_move: SignatureMove,
+    ) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            !self.figures.contains_key(&figure_id),
+            "Figure already exists."
+        );
+        assert!(stats.is_valid(), "Invalid stats.");
+
+        let figure = Figure {
+            name,
+            stats,
+            signature_move,
+            enabled: true,
+            version: 1,
+        };
+        self.figures.insert(figure_id.clone(), figure);
+
+        env::log_str(&format!("Figure {} added.", figure_id));
+    }
+
+    // Retired figures can't be picked for new duels, but ongoing ones play out as normal
+    pub fn retire_figure(&mut self, figure_id: FigureId) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let figure = self.figures.get_mut(&figure_id).expect("Figure not found.");
+        assert!(figure.enabled, "Figure already retired.");
+        figure.enabled = false;
+
+        env::log_str(&format!("Figure {} retired.", figure_id));
+    }
+
+    // Duels that already started keep the stats they were started with
+    pub fn rebalance_figure(&mut self, figure_id: FigureId, stats: Stats) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(stats.is_valid(), "Invalid stats.");
+
+        let figure = self.figures.get_mut(&figure_id).expect("Figure not found.");
+        figure.stats = stats;
+        figure.version += 1;
+
+        env::log_str(&format!(
+            "Figure {} rebalanced to version {}.",
+            figure_id, figure.version
+        ));
+    }
+
+    pub fn set_roast(&mut self, duel_id: U128, turn: usize, roast_cid: String) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let duel = self.duels.get_mut(&duel_id.0).expect("Duel not found.");
         assert!(turn < duel.turns.len(), "Turn has not been taken.");
         assert!(duel.turns[turn].roast_cid.is_none(), "Roast already set.");
 
@@ -480,22 +1269,117 @@ impl DuelManagerContract {
     ) -> PromiseOrValue<U128> {
         let token_in = env::predecessor_account_id();
         assert!(
-            token_in == self.ft_contract,
+            self.tokens.contains_key(&token_in),
             "{}",
             "The token is not supported"
         );
 
-        let balance = self.stakes.entry(sender_id.clone()).or_insert(0);
+        let balance = self
+            .stakes
+            .entry((sender_id.clone(), token_in.clone()))
+            .or_insert(0);
         *balance += amount.0;
-        self.total_stake += amount.0;
+        *self.total_stakes.entry(token_in.clone()).or_insert(0) += amount.0;
 
         let msg = serde_json::from_str::<Msg>(&msg);
         match msg {
-            Ok(Msg::CreateDuel { figure }) => {
-                self._create_duel(sender_id, figure, amount);
+            Ok(Msg::CreateDuel {
+                figure,
+                figure_hash,
+                secret_hash,
+                mut options,
+                opponent,
+            }) => {
+                Self::assert_paid_in(&token_in, options.token.as_ref().unwrap_or(&token_in));
+                options.token = Some(token_in);
+                let pick = Pick::new(figure, figure_hash);
+                self._create_duel(sender_id, pick, amount, secret_hash, options, opponent);
             }
-            Ok(Msg::AcceptDuel { duel_id, figure }) => {
-                self._accept_duel(sender_id, duel_id, figure);
+            Ok(Msg::AcceptDuel {
+                duel_id,
+                figure,
+                figure_hash,
+                secret_hash,
+            }) => {
+                Self::assert_paid_in(&token_in, &self.duel_token(duel_id));
+                let pick = Pick::new(figure, figure_hash);
+                self._accept_duel(sender_id, duel_id, pick, secret_hash);
+            }
+            Ok(Msg::JoinTournament {
+                tournament_id,
+                figure,
+            }) => {
+                Self::assert_paid_in(&token_in, &self.ft_contract);
+                self._join_tournament(sender_id, tournament_id, figure);
+            }
+            Ok(Msg::JoinQueue {
+                figure,
+                min_stake,
+                max_stake,
+                min_rating,
+                max_rating,
+            }) => {
+                Self::assert_paid_in(&token_in, &self.ft_contract);
+                self._join_queue(
+                    sender_id, figure, min_stake, max_stake, min_rating, max_rating,
+                );
+            }
+            Ok(Msg::PlaceBet { duel_id, side }) => {
+                Self::assert_paid_in(&token_in, &self.duel_token(duel_id));
+                self._place_bet(sender_id, duel_id, side, amount);
+            }
+            Ok(Msg::CreateRoyale {
+                figure,
+                secret_hash,
+                max_players,
+                knockout_percent,
+            }) => {
+                self._create_royale(
+                    sender_id,
+                    figure,
+                    secret_hash,
+                    token_in,
+                    amount,
+                    max_players,
+                    knockout_percent,
+                );
+            }
+            Ok(Msg::JoinRoyale {
+                royale_id,
+                figure,
+                secret_hash,
+            }) => {
+                let royale = self.royales.get(&royale_id.0).expect("Royale not found.");
+                Self::assert_paid_in(&token_in, &royale.token);
+                self._join_royale(sender_id, royale_id, figure, secret_hash);
+            }
+            Ok(Msg::CreateHouseDuel {
+                figure,
+                secret_hash,
+                house_figure,
+                options,
+            }) => {
+                Self::assert_paid_in(&token_in, &self.ft_contract);
+                self._create_house_duel(
+                    sender_id,
+                    figure,
+                    amount,
+                    secret_hash,
+                    house_figure,
+                    options,
+                );
+            }
+            Ok(Msg::FundBankroll) => {
+                Self::assert_paid_in(&token_in, &self.ft_contract);
+                self.fund_bankroll(sender_id, amount);
+            }
+            Ok(Msg::JoinTeam {
+                duel_id,
+                side,
+                figure,
+            }) => {
+                Self::assert_paid_in(&token_in, &self.duel_token(duel_id));
+                self._join_team(sender_id, duel_id, side, figure);
             }
             _ => (),
         }
@@ -503,56 +1387,190 @@ impl DuelManagerContract {
         PromiseOrValue::Value(U128(0))
     }
 
-    pub fn withdraw(&mut self, amount: U128) -> Promise {
+    // Balances in tokens taken off the whitelist can still be withdrawn
+    pub fn withdraw(&mut self, amount: U128, token: Option<AccountId>) -> Promise {
         let sender = env::predecessor_account_id();
-        let balance = self.stakes.entry(sender.clone()).or_insert(0);
+        let token = token.unwrap_or_else(|| self.ft_contract.clone());
+        let balance = self
+            .stakes
+            .entry((sender.clone(), token.clone()))
+            .or_insert(0);
         assert!(*balance >= amount.0, "Insufficient balance");
 
         *balance -= amount.0;
-        self.transfer(sender, amount)
+        self.transfer(&token, sender, amount)
     }
 
+    // Checks every token the contract has ever held against what it owes in that token,
+    // including tokens removed from the whitelist
     pub fn burn_excess(&mut self) -> Promise {
-        ext_ft_contract::ext(self.ft_contract.clone())
-            .ft_balance_of(env::current_account_id())
-            .then(Self::ext(env::current_account_id()).on_burn_excess())
+        self.total_stakes
+            .keys()
+            .map(|token| {
+                ext_ft_contract::ext(token.clone())
+                    .ft_balance_of(env::current_account_id())
+                    .then(Self::ext(env::current_account_id()).on_burn_excess(token.clone()))
+            })
+            .reduce(Promise::and)
+            .unwrap()
     }
 
     #[private]
     pub fn on_burn_excess(
         &mut self,
+        token: AccountId,
         #[callback_result] balance: Result<U128, PromiseError>,
     ) -> Option<Promise> {
         if let Ok(balance) = balance {
-            if balance.0 > self.total_stake {
-                return Some(self.burn(U128(balance.0 - self.total_stake)));
+            let total_stake = self.total_stakes.get(&token).copied().unwrap_or(0);
+            // The excess was never counted as owed, so it is burned without touching the total
+            if balance.0 > total_stake {
+                return Some(self.ft_burn(&token, U128(balance.0 - total_stake)));
             }
         }
         None
     }
 
-    fn _create_duel(&mut self, sender: AccountId, figure: HistoricalFigure, stake: U128) -> U128 {
-        let balance = self.stakes.entry(sender.clone()).or_insert(0);
+    #[private]
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_create_duel_card(
+        &mut self,
+        sender: AccountId,
+        figure: FigureId,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        options: DuelOptions,
+        opponent: Option<AccountId>,
+        #[callback_result] owns_card: Result<bool, PromiseError>,
+    ) -> U128 {
+        Self::assert_card(owns_card, &sender, &figure);
+        let pick = Pick::Open(figure);
+        self.open_duel(sender, pick, stake, secret_hash, options, opponent)
+    }
+
+    #[private]
+    pub fn on_accept_duel_card(
+        &mut self,
+        sender: AccountId,
+        duel_id: U128,
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+        #[callback_result] owns_card: Result<bool, PromiseError>,
+    ) {
+        Self::assert_card(owns_card, &sender, &figure);
+        self.join_duel(sender, duel_id, Pick::Open(figure), secret_hash);
+    }
+
+    fn _create_duel(
+        &mut self,
+        sender: AccountId,
+        pick: Pick,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        options: DuelOptions,
+        opponent: Option<AccountId>,
+    ) -> PromiseOrValue<U128> {
+        let figure = match pick {
+            Pick::Open(figure) if options.require_cards => figure,
+            pick => {
+                let duel_id = self.open_duel(sender, pick, stake, secret_hash, options, opponent);
+                return PromiseOrValue::Value(duel_id);
+            }
+        };
+
+        // Fail before the card lookup if the duel could never be opened
+        self.assert_duel_options(&options);
+        self.assert_figure(&figure);
+        let promise = self.check_card(&sender, &figure).then(
+            Self::ext(env::current_account_id()).on_create_duel_card(
+                sender,
+                figure,
+                stake,
+                secret_hash,
+                options,
+                opponent,
+            ),
+        );
+        PromiseOrValue::Promise(promise)
+    }
+
+    fn _accept_duel(
+        &mut self,
+        sender: AccountId,
+        duel_id: U128,
+        pick: Pick,
+        secret_hash: Base64VecU8,
+    ) -> Option<Promise> {
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.");
+        let figure = match pick {
+            Pick::Open(figure) if duel.require_cards => figure,
+            pick => {
+                self.join_duel(sender, duel_id, pick, secret_hash);
+                return None;
+            }
+        };
+
+        self.assert_figure(&figure);
+        let promise = self.check_card(&sender, &figure).then(
+            Self::ext(env::current_account_id()).on_accept_duel_card(
+                sender,
+                duel_id,
+                figure,
+                secret_hash,
+            ),
+        );
+        Some(promise)
+    }
+
+    fn open_duel(
+        &mut self,
+        sender: AccountId,
+        pick: Pick,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        options: DuelOptions,
+        opponent: Option<AccountId>,
+    ) -> U128 {
+        assert!(secret_hash.0.len() == 32, "Secret hash must be 32 bytes.");
+        assert!(
+            opponent.as_ref() != Some(&sender),
+            "Cannot challenge yourself."
+        );
+        self.assert_pick(&pick, options.blind_pick, options.draft);
+        self.assert_duel_options(&options);
+
+        let token = options
+            .token
+            .clone()
+            .unwrap_or_else(|| self.ft_contract.clone());
+        self.assert_token(&token);
+        let balance = self
+            .stakes
+            .entry((sender.clone(), token.clone()))
+            .or_insert(0);
 
-        // assert!(stake >= U128(MIN_STAKE), "Minimum stake required.");
+        assert!(
+            stake.0 >= self.tokens[&token].min_stake.0,
+            "Minimum stake required."
+        );
         assert!(*balance >= stake.0, "Insufficient balance");
 
         *balance -= stake.0;
 
         let duel_id = self.next_duel_id;
-        let duel = Duel {
-            id: U128(duel_id),
-            creation_time: env::block_timestamp(),
-            start_time: None,
+        let mut duel = Duel::new(
+            duel_id,
             stake,
-            player_a: sender.clone(),
-            figure_a: figure,
-            player_b: None,
-            figure_b: None,
-            turns: Vec::new(),
-            winner: None,
-        };
+            token,
+            sender.clone(),
+            pick,
+            &options,
+            &self.config,
+        );
+        duel.commitment_a = Some(secret_hash);
+        duel.opponent = opponent;
 
+        self.index_duel(&duel, None);
         self.duels.insert(duel_id, duel);
         self.next_duel_id += 1;
 
@@ -560,37 +1578,629 @@ impl DuelManagerContract {
         U128(duel_id)
     }
 
-    fn _accept_duel(&mut self, sender: AccountId, duel_id: U128, figure: HistoricalFigure) {
-        let duel = self.duels.get_mut(&duel_id.0).expect("Duel not found.");
-        let balance = self.stakes.entry(sender.clone()).or_insert(0);
+    fn join_duel(
+        &mut self,
+        sender: AccountId,
+        duel_id: U128,
+        pick: Pick,
+        secret_hash: Base64VecU8,
+    ) {
+        assert!(secret_hash.0.len() == 32, "Secret hash must be 32 bytes.");
+        let mut duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+        self.assert_pick(&pick, duel.blind_pick, duel.draft);
+        let balance = self
+            .stakes
+            .entry((sender.clone(), duel.token.clone()))
+            .or_insert(0);
 
         assert!(duel.player_b.is_none(), "Duel already accepted.");
         assert!(sender != duel.player_a, "Account is already participating.");
         assert!(
-            figure != duel.figure_a,
-            "Historical figure already selected."
+            duel.opponent.is_none() || duel.opponent.as_ref() == Some(&sender),
+            "Duel is reserved for another opponent."
         );
+        if let Pick::Open(figure) = &pick {
+            assert!(
+                !duel.figures().any(|taken| taken == figure),
+                "Historical figure already selected."
+            );
+        }
         assert!(*balance >= duel.stake.0, "Insufficient balance");
 
         *balance -= duel.stake.0;
 
-        duel.start_time = Some(env::block_timestamp());
-        duel.player_b = Some(sender.clone());
-        duel.figure_b = Some(figure);
+        let previous = duel.status();
+        duel.commitment_b = Some(secret_hash);
+        match pick {
+            Pick::Open(figure) if duel.team => {
+                duel.player_b = Some(sender.clone());
+                duel.figure_b = Some(figure);
+                self.start_team_duel(&mut duel);
+            }
+            Pick::Open(figure) => self.start_duel(&mut duel, sender.clone(), figure),
+            // Figures are loaded once both are revealed
+            Pick::Blind(figure_hash) => {
+                duel.figure_commitment_b = Some(figure_hash);
+                duel.start(sender.clone());
+            }
+            Pick::Draft => {
+                duel.start(sender.clone());
+                duel.draft_deadline = Some(env::block_timestamp() + duel.draft_timeout);
+            }
+        }
+
+        self.index_duel(&duel, Some(previous));
+        self.duels.insert(duel_id.0, duel);
 
         env::log_str(&format!("Duel {} accepted by {}.", duel_id.0, sender));
     }
 
-    fn transfer(&mut self, sender: AccountId, amount: U128) -> Promise {
-        self.total_stake -= amount.0;
-        ext_ft_contract::ext(self.ft_contract.clone())
+    fn _join_team(&mut self, sender: AccountId, duel_id: U128, side: Side, figure: FigureId) {
+        let mut duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+        assert!(duel.team, "Duel is not a team duel.");
+        assert!(duel.start_time.is_none(), "Duel already started.");
+        assert!(
+            !duel.accounts().any(|account_id| *account_id == sender),
+            "Account is already participating."
+        );
+        self.assert_figure(&figure);
+        assert!(
+            !duel.figures().any(|taken| *taken == figure),
+            "Historical figure already selected."
+        );
+
+        let slot = match side {
+            Side::PlayerA => &mut duel.partner_a,
+            Side::PlayerB => &mut duel.partner_b,
+        };
+        assert!(slot.is_none(), "Team is already full.");
+
+        let balance = self
+            .stakes
+            .entry((sender.clone(), duel.token.clone()))
+            .or_insert(0);
+        assert!(*balance >= duel.stake.0, "Insufficient balance");
+        *balance -= duel.stake.0;
+
+        *slot = Some(Partner {
+            account_id: sender.clone(),
+            figure,
+            version: 0,
+            base_stats: Stats::default(),
+            level: 0,
+        });
+
+        let previous = duel.status();
+        self.start_team_duel(&mut duel);
+
+        self.index_duel(&duel, Some(previous));
+        self.duels.insert(duel_id.0, duel);
+
+        env::log_str(&format!("{} joined team duel {}.", sender, duel_id.0));
+    }
+
+    // The last of the four players to join starts the duel
+    fn start_team_duel(&self, duel: &mut Duel) {
+        if duel.is_full() {
+            duel.start(duel.player_b.clone().unwrap());
+            self.load_figures(duel);
+        }
+    }
+
+    pub(crate) fn figure_level(&self, account_id: &AccountId, figure: &str) -> u8 {
+        let xp = self
+            .figure_xp
+            .get(&(account_id.clone(), figure.to_string()));
+        level_for_xp(xp.copied().unwrap_or(0))
+    }
+
+    pub(crate) fn assert_figure(&self, figure: &str) {
+        assert!(
+            self.figures
+                .get(figure)
+                .is_some_and(|figure| figure.enabled),
+            "Figure is not available."
+        );
+    }
+
+    fn assert_pick(&self, pick: &Pick, blind_pick: bool, draft: bool) {
+        match pick {
+            Pick::Open(figure) => {
+                assert!(!blind_pick, "Duel requires a blind pick.");
+                assert!(!draft, "Figures are picked in the draft.");
+                self.assert_figure(figure);
+            }
+            Pick::Blind(figure_hash) => {
+                assert!(blind_pick, "Duel does not use blind picks.");
+                assert!(figure_hash.0.len() == 32, "Figure hash must be 32 bytes.");
+            }
+            Pick::Draft => assert!(draft, "Duel requires a figure."),
+        }
+    }
+
+    pub(crate) fn start_duel(&self, duel: &mut Duel, player_b: AccountId, figure_b: FigureId) {
+        duel.figure_b = Some(figure_b);
+        duel.start(player_b);
+        self.load_figures(duel);
+    }
+
+    // Snapshots both players' figure stats and levels as the fight begins
+    pub(crate) fn load_figures(&self, duel: &mut Duel) {
+        self.load_partners(duel);
+        let loadout_a = self.loadout(&duel.player_a, duel.figure_a.as_ref().unwrap());
+        let loadout_b = self.loadout(
+            duel.player_b.as_ref().unwrap(),
+            duel.figure_b.as_ref().unwrap(),
+        );
+        duel.load(&loadout_a, &loadout_b);
+        duel.extend_deadline();
+        duel.start_clock();
+    }
+
+    // Partner stats are loaded before the side hit points are reset
+    fn load_partners(&self, duel: &mut Duel) {
+        for partner in [&mut duel.partner_a, &mut duel.partner_b]
+            .into_iter()
+            .flatten()
+        {
+            let loadout = self.loadout(&partner.account_id, &partner.figure);
+            partner.version = loadout.version;
+            partner.base_stats = loadout.stats;
+            partner.level = loadout.level;
+        }
+    }
+
+    fn loadout(&self, account_id: &AccountId, figure: &str) -> Loadout {
+        let data = self.figures.get(figure).expect("Figure not found.");
+        Loadout {
+            version: data.version,
+            stats: data.stats,
+            level: self.figure_level(account_id, figure),
+        }
+    }
+
+    fn check_card(&self, account_id: &AccountId, figure: &str) -> Promise {
+        let nft_contract = self
+            .nft_contract
+            .clone()
+            .expect("Figure cards are not enabled.");
+        ext_nft_contract::ext(nft_contract).owns_figure(account_id.clone(), figure.to_string())
+    }
+
+    fn assert_card(owns_card: Result<bool, PromiseError>, account_id: &AccountId, figure: &str) {
+        assert!(
+            matches!(owns_card, Ok(true)),
+            "{} does not hold a {} card.",
+            account_id,
+            figure
+        );
+    }
+
+    fn assert_duel_options(&self, options: &DuelOptions) {
+        let turns = options.turns();
+        assert!(
+            turns % 2 == 0 && turns >= self.min_turns && turns <= self.max_turns,
+            "Turn count must be even and within bounds."
+        );
+        assert!(
+            matches!(options.rounds(), 1 | 3 | 5),
+            "Rounds must be 1, 3 or 5."
+        );
+        // The card check needs the figure up front
+        assert!(
+            !(options.blind_pick && options.require_cards),
+            "Blind pick duels cannot require figure cards."
+        );
+        if options.draft {
+            assert!(
+                !options.blind_pick && !options.require_cards,
+                "Draft duels cannot use blind picks or require figure cards."
+            );
+            assert!(
+                matches!(options.bans(), 1 | 2),
+                "Drafts must have 1 or 2 bans per player."
+            );
+        }
+        if let Some(turn_timeout) = options.turn_timeout {
+            Self::assert_turn_timeout(turn_timeout);
+        }
+        if let Some(time_control) = options.time_control {
+            assert!(
+                (MIN_CLOCK..=MAX_TURN_TIMEOUT).contains(&time_control.base),
+                "Clocks must start between 1 minute and 7 days."
+            );
+            assert!(
+                time_control.increment <= MAX_INCREMENT,
+                "Clock increments can be at most 1 day."
+            );
+        }
+        if options.team {
+            assert!(
+                !options.blind_pick && !options.draft && !options.require_cards,
+                "Team duels cannot use blind picks, drafts or figure cards."
+            );
+            // Every player gets the same number of turns
+            assert!(
+                turns % 4 == 0,
+                "Team duels need a turn count divisible by 4."
+            );
+        }
+    }
+
+    fn assert_token(&self, token: &AccountId) {
+        assert!(
+            self.tokens.contains_key(token),
+            "The token is not supported"
+        );
+    }
+
+    fn assert_paid_in(token_in: &AccountId, token: &AccountId) {
+        assert!(token_in == token, "Deposit must be in {}.", token);
+    }
+
+    fn duel_token(&self, duel_id: U128) -> AccountId {
+        self.duels
+            .get(&duel_id.0)
+            .expect("Duel not found.")
+            .token
+            .clone()
+    }
+
+    fn assert_turn_timeout(turn_timeout: u64) {
+        assert!(
+            (MIN_TURN_TIMEOUT..=MAX_TURN_TIMEOUT).contains(&turn_timeout),
+            "Turn timeout must be between 1 hour and 7 days."
+        );
+    }
+
+    fn settle_duel(
+        &mut self,
+        mut duel: Duel,
+        winner: Winner,
+        reason: WinReason,
+    ) -> Option<Promise> {
+        duel.winner = Some(winner);
+        duel.win_reason = Some(reason);
+
+        let winners: Vec<AccountId> = match winner {
+            Winner::PlayerA => duel.side(true).into_iter().cloned().collect(),
+            Winner::PlayerB => duel.side(false).into_iter().cloned().collect(),
+            Winner::Draw => Vec::new(),
+        };
+        if winners.is_empty() {
+            env::log_str(&format!("Duel {} finished! Result: draw!", duel.id.0));
+        } else {
+            env::log_str(&format!(
+                "Duel {} finished! Result: {} won!",
+                duel.id.0,
+                winners.iter().join(" & ")
+            ));
+        }
+
+        // Bracket duels are staked through the tournament entry fees instead
+        let promise = if duel.house {
+            self.settle_house_stakes(&duel, winner)
+        } else if duel.tournament_id.is_some() {
+            None
+        } else if !winners.is_empty() {
+            // The fee and any rounding dust from splitting the pot are burned
+            let share = duel.payout_share();
+            self.burn(
+                &duel.token,
+                U128(duel.pot() - share * winners.len() as u128),
+            );
+            winners
+                .into_iter()
+                .map(|winner| self.transfer(&duel.token, winner, U128(share)))
+                .reduce(Promise::and)
+        } else {
+            Some(self.refund_players(&duel))
+        };
+
+        self.settle_bets(&duel, winner);
+        self.record_stats(&duel, winner);
+        self.award_xp(&duel, winner);
+        if !duel.house {
+            self.update_ratings(&duel.player_a, duel.player_b.as_ref().unwrap(), winner);
+        }
+        if let (Some(partner_a), Some(partner_b)) = (&duel.partner_a, &duel.partner_b) {
+            self.update_ratings(&partner_a.account_id, &partner_b.account_id, winner);
+        }
+
+        let tournament_id = duel.tournament_id;
+        self.index_duel(&duel, Some(DuelStatus::Active));
+        self.duels.insert(duel.id.0, duel);
+
+        match tournament_id {
+            Some(tournament_id) => self.advance_tournament(tournament_id).or(promise),
+            None => promise,
+        }
+    }
+
+    // Winnings and losses are tracked in $ROASTI only
+    fn record_stats(&mut self, duel: &Duel, winner: Winner) {
+        let (stake, payout) = if duel.token == self.ft_contract {
+            (duel.stake.0, duel.payout_share())
+        } else {
+            (0, 0)
+        };
+        let side_a = duel.side(true);
+
+        // The house keeps no stats
+        for account_id in duel
+            .players()
+            .filter(|account_id| !duel.is_house(account_id))
+        {
+            let result = if side_a.contains(&account_id) {
+                winner
+            } else {
+                winner.flipped()
+            };
+            let (dealt, taken) = duel.damage_by(account_id);
+            self.record_result(account_id, result, dealt, taken, stake, payout);
+        }
+    }
+
+    // `result` is from the account's point of view, so `PlayerA` is a win
+    fn record_result(
+        &mut self,
+        account_id: &AccountId,
+        result: Winner,
+        dealt: u32,
+        taken: u32,
+        stake: u128,
+        payout: u128,
+    ) {
+        let mut stats = self.get_player_stats(account_id.clone());
+        let (old_wins, old_damage) = (stats.wins, stats.damage_dealt);
+
+        match result {
+            Winner::PlayerA => {
+                stats.wins += 1;
+                stats.roasti_won.0 += payout - stake;
+            }
+            Winner::PlayerB => {
+                stats.losses += 1;
+                stats.roasti_lost.0 += stake;
+            }
+            Winner::Draw => stats.draws += 1,
+        }
+        stats.damage_dealt += dealt;
+        stats.damage_taken += taken;
+
+        Self::reindex(&mut self.wins_index, account_id, old_wins, stats.wins);
+        Self::reindex(
+            &mut self.damage_index,
+            account_id,
+            old_damage,
+            stats.damage_dealt,
+        );
+        self.player_stats.insert(account_id.clone(), stats);
+    }
+
+    fn award_xp(&mut self, duel: &Duel, winner: Winner) {
+        let partners = [
+            (&duel.partner_a, winner),
+            (&duel.partner_b, winner.flipped()),
+        ]
+        .into_iter()
+        .filter_map(|(partner, result)| {
+            let partner = partner.as_ref()?;
+            Some((
+                partner.account_id.clone(),
+                Some(partner.figure.clone()),
+                result,
+            ))
+        });
+
+        // A player who forfeited without revealing has no figure to level up
+        for (account_id, figure, result) in [
+            (duel.player_a.clone(), duel.figure_a.clone(), winner),
+            (
+                duel.player_b.clone().unwrap(),
+                duel.figure_b.clone(),
+                winner.flipped(),
+            ),
+        ]
+        .into_iter()
+        .chain(partners)
+        .filter(|(account_id, _, _)| !duel.is_house(account_id))
+        {
+            if let Some(figure) = figure {
+                self.gain_xp(&account_id, &figure, result);
+            }
+        }
+    }
+
+    fn gain_xp(&mut self, account_id: &AccountId, figure: &str, result: Winner) {
+        let gained = match result {
+            Winner::PlayerA => WIN_XP,
+            Winner::Draw => DRAW_XP,
+            Winner::PlayerB => LOSS_XP,
+        };
+
+        let xp = self
+            .figure_xp
+            .entry((account_id.clone(), figure.to_string()))
+            .or_insert(0);
+        let level = level_for_xp(*xp);
+        *xp += gained;
+
+        if level_for_xp(*xp) > level {
+            env::log_str(&format!(
+                "{}'s {} reached level {}.",
+                account_id,
+                figure,
+                level_for_xp(*xp)
+            ));
+        }
+    }
+
+    fn update_ratings(&mut self, player_a: &AccountId, player_b: &AccountId, winner: Winner) {
+        let rating_a = self.get_rating(player_a.clone());
+        let rating_b = self.get_rating(player_b.clone());
+
+        let score_a = match winner {
+            Winner::PlayerA => 1000,
+            Winner::PlayerB => 0,
+            Winner::Draw => 500,
+        };
+        let surprise = score_a - Self::expected_score(rating_a, rating_b);
+        // Rounded to the nearest point
+        let change = (RATING_K_FACTOR * surprise + 500 * surprise.signum()) / 1000;
+
+        for (account_id, old, change) in
+            [(player_a, rating_a, change), (player_b, rating_b, -change)]
+        {
+            let new = (old as i64 + change).max(0) as u32;
+            Self::reindex(&mut self.rating_index, account_id, old, new);
+            self.ratings.insert(account_id.clone(), new);
+        }
+    }
+
+    // In thousandths, interpolated between the table steps. Leads past 800 points count as 800.
+    fn expected_score(rating: u32, opponent: u32) -> i64 {
+        let lead = rating.abs_diff(opponent).min(800);
+        let step = (lead / RATING_STEP) as usize;
+        let low = EXPECTED_SCORES[step];
+        let high = EXPECTED_SCORES[(step + 1).min(EXPECTED_SCORES.len() - 1)];
+        let expected = low + (high - low) * (lead % RATING_STEP) / RATING_STEP;
+
+        if rating >= opponent {
+            expected as i64
+        } else {
+            1000 - expected as i64
+        }
+    }
+
+    // Moves the duel from its previous status bucket and lists it for every account involved
+    pub(crate) fn index_duel(&mut self, duel: &Duel, previous: Option<DuelStatus>) {
+        if let Some(previous) = previous {
+            self.status_index.remove(&(previous, duel.id.0));
+        }
+        self.status_index.insert((duel.status(), duel.id.0), ());
+        for account_id in duel.accounts() {
+            self.account_index
+                .insert((account_id.clone(), duel.id.0), ());
+        }
+        if let Some(opponent) = &duel.opponent {
+            let key = (opponent.clone(), duel.id.0);
+            if duel.status() == DuelStatus::Challenged {
+                self.challenge_index.insert(key, ());
+            } else {
+                self.challenge_index.remove(&key);
+            }
+        }
+    }
+
+    fn remove_duel(&mut self, duel_id: u128) {
+        let duel = self.duels.remove(&duel_id).expect("Duel not found.");
+        self.status_index.remove(&(duel.status(), duel_id));
+        for account_id in duel.accounts() {
+            self.account_index.remove(&(account_id.clone(), duel_id));
+        }
+        if let Some(opponent) = duel.opponent {
+            self.challenge_index.remove(&(opponent, duel_id));
+        }
+    }
+
+    fn duels_by_status(
+        &self,
+        status: DuelStatus,
+        from_id: Option<U128>,
+        count: usize,
+    ) -> Vec<Duel> {
+        let end = match from_id {
+            Some(from_id) => Bound::Excluded((status, from_id.0)),
+            None => Bound::Included((status, u128::MAX)),
+        };
+
+        self.status_index
+            .range((Bound::Included((status, 0)), end))
+            .rev()
+            .take(count)
+            .map(|((_, duel_id), _)| self.duels.get(duel_id).unwrap().clone())
+            .collect()
+    }
+
+    fn account_duel_ids(
+        &self,
+        account_id: &AccountId,
+        from_id: Option<U128>,
+    ) -> impl Iterator<Item = u128> + '_ {
+        let end = match from_id {
+            Some(from_id) => Bound::Excluded((account_id.clone(), from_id.0)),
+            None => Bound::Included((account_id.clone(), u128::MAX)),
+        };
+
+        self.account_index
+            .range((Bound::Included((account_id.clone(), 0)), end))
+            .rev()
+            .map(|((_, duel_id), _)| *duel_id)
+    }
+
+    fn reindex(
+        index: &mut TreeMap<(u32, AccountId), ()>,
+        account_id: &AccountId,
+        old: u32,
+        new: u32,
+    ) {
+        index.remove(&(old, account_id.clone()));
+        if new > 0 {
+            index.insert((new, account_id.clone()), ());
+        }
+    }
+
+    fn leaderboard(
+        index: &TreeMap<(u32, AccountId), ()>,
+        from: Option<(u32, AccountId)>,
+        count: usize,
+    ) -> Vec<LeaderboardItem> {
+        let end = match from {
+            Some(from) => Bound::Excluded(from),
+            None => Bound::Unbounded,
+        };
+
+        index
+            .range((Bound::Unbounded, end))
+            .rev()
+            .take(count)
+            .map(|(key, _)| key)
+            .map(|(value, account_id)| LeaderboardItem {
+                account_id: account_id.clone(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    fn refund_players(&mut self, duel: &Duel) -> Promise {
+        duel.players()
+            .map(|player| self.transfer(&duel.token, player.clone(), duel.stake))
+            .reduce(Promise::and)
+            .unwrap()
+    }
+
+    fn transfer(&mut self, token: &AccountId, sender: AccountId, amount: U128) -> Promise {
+        *self.total_stakes.entry(token.clone()).or_insert(0) -= amount.0;
+        ext_ft_contract::ext(token.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .ft_transfer(sender, amount, None)
     }
 
-    fn burn(&mut self, amount: U128) -> Promise {
-        ext_ft_contract::ext(self.ft_contract.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .ft_burn(amount)
+    // Burns part of what the contract owes, such as a fee taken from a pot
+    fn burn(&mut self, token: &AccountId, amount: U128) -> Promise {
+        *self.total_stakes.entry(token.clone()).or_insert(0) -= amount.0;
+        self.ft_burn(token, amount)
+    }
+
+    // Only $ROASTI has an `ft_burn`. Other tokens go to the treasury instead.
+    fn ft_burn(&self, token: &AccountId, amount: U128) -> Promise {
+        let ft_contract =
+            ext_ft_contract::ext(token.clone()).with_attached_deposit(NearToken::from_yoctonear(1));
+        if *token == self.ft_contract {
+            ft_contract.ft_burn(amount)
+        } else {
+            ft_contract.ft_transfer(self.treasury_id.clone(), amount, None)
+        }
     }
 }
@@ -0,0 +1,140 @@
+use crate::*;
+
+// Matching scans the whole queue, so it is kept short
+const MAX_QUEUE_SIZE: u32 = 100;
+
+#[near_bindgen]
+impl DuelManagerContract {
+    pub fn get_queue(&self) -> Vec<QueueEntry> {
+        self.queue.values().cloned().collect()
+    }
+
+    #[payable]
+    pub fn join_queue(
+        &mut self,
+        figure: FigureId,
+        min_stake: U128,
+        max_stake: U128,
+        min_rating: Option<u32>,
+        max_rating: Option<u32>,
+    ) -> Option<U128> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._join_queue(sender, figure, min_stake, max_stake, min_rating, max_rating)
+    }
+
+    #[payable]
+    pub fn leave_queue(&mut self) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let entry = self.queue.remove(&sender).expect("Account is not queued.");
+        *self
+            .stakes
+            .entry((sender.clone(), self.ft_contract.clone()))
+            .or_insert(0) += entry.max_stake.0;
+
+        env::log_str(&format!("{} left the matchmaking queue.", sender));
+    }
+
+    pub(crate) fn _join_queue(
+        &mut self,
+        sender: AccountId,
+        figure: FigureId,
+        min_stake: U128,
+        max_stake: U128,
+        min_rating: Option<u32>,
+        max_rating: Option<u32>,
+    ) -> Option<U128> {
+        assert!(
+            !self.queue.contains_key(&sender),
+            "Account is already queued."
+        );
+        assert!(min_stake.0 <= max_stake.0, "Invalid stake range.");
+        assert!(
+            min_stake.0 >= self.tokens[&self.ft_contract].min_stake.0,
+            "Minimum stake required."
+        );
+        self.assert_figure(&figure);
+
+        // The top of the range is held until a match is found
+        let balance = self
+            .stakes
+            .entry((sender.clone(), self.ft_contract.clone()))
+            .or_insert(0);
+        assert!(*balance >= max_stake.0, "Insufficient balance");
+        *balance -= max_stake.0;
+
+        let entry = QueueEntry {
+            account_id: sender.clone(),
+            figure: figure.clone(),
+            min_stake,
+            max_stake,
+            min_rating,
+            max_rating,
+            creation_time: env::block_timestamp(),
+        };
+
+        // Pair with the longest-waiting compatible entry
+        let rating = self.get_rating(sender.clone());
+        let opponent = self
+            .queue
+            .values()
+            .filter(|other| {
+                other.figure != figure
+                    && entry.common_stake(other).is_some()
+                    && entry.accepts_rating(self.get_rating(other.account_id.clone()))
+                    && other.accepts_rating(rating)
+            })
+            .min_by_key(|other| other.creation_time)
+            .cloned();
+
+        let Some(opponent) = opponent else {
+            assert!(
+                self.queue.len() < MAX_QUEUE_SIZE,
+                "The matchmaking queue is full."
+            );
+            self.queue.insert(sender.clone(), entry);
+            env::log_str(&format!("{} joined the matchmaking queue.", sender));
+            return None;
+        };
+
+        self.queue.remove(&opponent.account_id);
+        let stake = entry.common_stake(&opponent).unwrap();
+        for queued in [&entry, &opponent] {
+            *self
+                .stakes
+                .entry((queued.account_id.clone(), self.ft_contract.clone()))
+                .or_insert(0) += queued.max_stake.0 - stake;
+        }
+
+        let duel_id = self.next_duel_id;
+        let mut duel = Duel::new(
+            duel_id,
+            U128(stake),
+            self.ft_contract.clone(),
+            opponent.account_id.clone(),
+            Pick::Open(opponent.figure),
+            &DuelOptions::default(),
+            &self.config,
+        );
+        self.start_duel(&mut duel, sender.clone(), figure);
+
+        self.index_duel(&duel, None);
+        self.duels.insert(duel_id, duel);
+        self.next_duel_id += 1;
+
+        env::log_str(&format!(
+            "Duel {} matched {} against {}.",
+            duel_id, opponent.account_id, sender
+        ));
+        Some(U128(duel_id))
+    }
+}
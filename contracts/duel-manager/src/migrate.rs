@@ -0,0 +1,171 @@
+use crate::*;
+
+// The fee every duel paid before it became configurable
+const LEGACY_FEE_PERCENT: u8 = 10;
+
+// The layout of the first deployment, when figures were an enum stored as their index and
+// $ROASTI was the only token
+#[near(serializers = [borsh])]
+pub struct ContractV0 {
+    admin_id: AccountId,
+    ft_contract: AccountId,
+    duels: IterableMap<u128, DuelV0>,
+    next_duel_id: u128,
+    stakes: LookupMap<AccountId, u128>,
+    total_stake: u128,
+}
+
+#[near(serializers = [borsh])]
+pub struct DuelV0 {
+    id: U128,
+    creation_time: u64,
+    start_time: Option<u64>,
+    stake: U128,
+    player_a: AccountId,
+    figure_a: u8,
+    player_b: Option<AccountId>,
+    figure_b: Option<u8>,
+    turns: Vec<TurnV0>,
+    winner: Option<Winner>,
+}
+
+#[near(serializers = [borsh])]
+pub struct TurnV0 {
+    creation_time: u64,
+    damage: u8,
+    style: RoastStyle,
+    roast_cid: Option<String>,
+}
+
+#[near_bindgen]
+impl DuelManagerContract {
+    // Upgrades a contract deployed with the first layout. Its duels stay under their old prefix
+    // until `migrate_duels` has moved them all.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: ContractV0 = env::state_read().expect("No state to migrate.");
+
+        let mut contract = Self::new(old.admin_id, old.ft_contract.clone());
+        contract.next_duel_id = old.next_duel_id;
+        contract
+            .total_stakes
+            .insert(old.ft_contract, old.total_stake);
+        contract.legacy_duels = Some(old.duels);
+        contract
+    }
+
+    // Moves up to `count` legacy duels, oldest first, and returns how many are left. Finished
+    // duels are converted, indexed and counted in stats and ratings. Unfinished ones are refunded.
+    pub fn migrate_duels(&mut self, count: u32) -> u32 {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let legacy_duels = self.legacy_duels.as_mut().expect("No duels to migrate.");
+        let duel_ids = legacy_duels
+            .keys()
+            .copied()
+            .sorted()
+            .take(count as usize)
+            .collect_vec();
+        let old_duels = duel_ids
+            .iter()
+            .map(|duel_id| legacy_duels.remove(duel_id).unwrap())
+            .collect_vec();
+        let remaining = legacy_duels.len();
+        if remaining == 0 {
+            self.legacy_duels = None;
+        }
+
+        for old in old_duels {
+            self.migrate_balance(old.player_a.clone());
+            if let Some(player_b) = &old.player_b {
+                self.migrate_balance(player_b.clone());
+            }
+            self.migrate_duel(old);
+        }
+        remaining
+    }
+
+    // Balances from the first layout are keyed by account alone. Anyone can move one over.
+    pub fn migrate_balance(&mut self, account_id: AccountId) {
+        let mut old_stakes = LookupMap::<AccountId, u128>::new(b"s");
+        if let Some(amount) = old_stakes.remove(&account_id) {
+            let key = (account_id, self.ft_contract.clone());
+            *self.stakes.entry(key).or_insert(0) += amount;
+        }
+    }
+
+    fn migrate_duel(&mut self, old: DuelV0) {
+        let Some(winner) = old.winner else {
+            for player in std::iter::once(old.player_a).chain(old.player_b) {
+                let key = (player, self.ft_contract.clone());
+                *self.stakes.entry(key).or_insert(0) += old.stake.0;
+            }
+            env::log_str(&format!("Legacy duel {} refunded.", old.id.0));
+            return;
+        };
+
+        let player_b = old.player_b.unwrap();
+        let figure_b = launch_figure(old.figure_b.unwrap());
+        let mut duel = Duel::new(
+            old.id.0,
+            old.stake,
+            self.ft_contract.clone(),
+            old.player_a.clone(),
+            Pick::Open(launch_figure(old.figure_a)),
+            &DuelOptions::default(),
+            &self.config,
+        );
+        duel.creation_time = old.creation_time;
+        duel.start_time = old.start_time;
+        duel.fee_percent = LEGACY_FEE_PERCENT;
+        duel.player_b = Some(player_b.clone());
+        duel.figure_b = Some(figure_b.clone());
+        let loadout_a = self.loadout(&duel.player_a, duel.figure_a.as_ref().unwrap());
+        let loadout_b = self.loadout(&player_b, &figure_b);
+        duel.load(&loadout_a, &loadout_b);
+
+        // Player A always went first, and a roast strong against the one before it could roll up
+        // to five more. The dice before the weakness penalty weren't stored.
+        let mut last_style = None;
+        for (i, turn) in old.turns.into_iter().enumerate() {
+            let player_a_turn = i % 2 == 0;
+            let (attacker, defender, figure, stats) = if player_a_turn {
+                (&duel.player_a, &player_b, &duel.figure_a, loadout_a.stats)
+            } else {
+                (&player_b, &duel.player_a, &duel.figure_b, loadout_b.stats)
+            };
+            let base_stat = turn.style.stat(&stats);
+            let strong = last_style.is_some_and(|last| turn.style.is_strong_against(last));
+            last_style = Some(turn.style);
+            duel.turns.push(Turn {
+                creation_time: turn.creation_time,
+                attacker: attacker.clone(),
+                defender: defender.clone(),
+                figure: figure.clone().unwrap(),
+                base_stat,
+                max_damage: if strong { base_stat + 5 } else { base_stat },
+                roll: turn.damage,
+                bonus: 0,
+                penalty: 0,
+                damage: turn.damage,
+                style: turn.style,
+                signature: None,
+                critical: false,
+                dodged: false,
+                round: 0,
+                seed: Base64VecU8(Vec::new()),
+                roast_cid: turn.roast_cid,
+            });
+        }
+        duel.round_results.push(winner);
+        duel.winner = Some(winner);
+        duel.win_reason = Some(WinReason::Decision);
+
+        self.record_stats(&duel, winner);
+        self.update_ratings(&duel.player_a, &player_b, winner);
+        self.index_duel(&duel, None);
+        self.duels.insert(duel.id.0, duel);
+    }
+}
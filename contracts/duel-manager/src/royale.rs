@@ -0,0 +1,456 @@
+use crate::*;
+
+const MIN_ROYALE_PLAYERS: u8 = 3;
+const MAX_ROYALE_PLAYERS: u8 = 8;
+const MAX_KNOCKOUT_PERCENT: u8 = 50;
+
+#[near_bindgen]
+impl DuelManagerContract {
+    pub fn get_royale(&self, royale_id: U128) -> Option<Royale> {
+        self.royales.get(&royale_id.0).cloned()
+    }
+
+    // Newest first. Pass the last royale id of a page as `from_id` to get the next one.
+    pub fn get_royales(&self, from_id: Option<U128>, count: usize) -> Vec<Royale> {
+        let end = match from_id {
+            Some(from_id) => Bound::Excluded(from_id.0),
+            None => Bound::Unbounded,
+        };
+
+        self.royales
+            .range((Bound::Unbounded, end))
+            .rev()
+            .take(count)
+            .map(|(_, royale)| royale.clone())
+            .collect()
+    }
+
+    // A `knockout_percent` of 0 plays every fighter down to zero hit points. The `token`
+    // defaults to $ROASTI. Every fighter commits a `secret_hash` as they join and reveals the
+    // secret once the royale is full.
+    #[payable]
+    pub fn create_royale(
+        &mut self,
+        figure: FigureId,
+        stake: U128,
+        secret_hash: Base64VecU8,
+        max_players: u8,
+        knockout_percent: Option<u8>,
+        token: Option<AccountId>,
+    ) -> U128 {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let token = token.unwrap_or_else(|| self.ft_contract.clone());
+        self._create_royale(
+            sender,
+            figure,
+            secret_hash,
+            token,
+            stake,
+            max_players,
+            knockout_percent,
+        )
+    }
+
+    #[payable]
+    pub fn join_royale(&mut self, royale_id: U128, figure: FigureId, secret_hash: Base64VecU8) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._join_royale(sender, royale_id, figure, secret_hash);
+    }
+
+    // Any fighter can cancel a royale that hasn't filled up in time
+    #[payable]
+    pub fn cancel_royale(&mut self, royale_id: U128) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let royale = self.royales.get(&royale_id.0).expect("Royale not found.");
+        assert!(royale.start_time.is_none(), "Royale already started.");
+        assert!(
+            royale.fighter(&sender).is_some(),
+            "Sender must be a fighter."
+        );
+        assert!(
+            env::block_timestamp() >= royale.creation_time + royale.accept_timeout,
+            "You must wait for the acceptance window to pass to cancel an unfilled royale."
+        );
+
+        let royale = self.royales.remove(&royale_id.0).unwrap();
+        self.refund_royale(royale);
+    }
+
+    #[payable]
+    pub fn reveal_royale_secret(&mut self, royale_id: U128, secret: Base64VecU8) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+        assert!(secret.0.len() == 32, "Secret must be 32 bytes.");
+
+        let sender = env::predecessor_account_id();
+        let royale = self
+            .royales
+            .get_mut(&royale_id.0)
+            .expect("Royale not found.");
+        assert!(royale.start_time.is_some(), "Royale has not started yet.");
+        assert!(royale.winner.is_none(), "Royale already completed.");
+
+        let index = royale.fighter(&sender).expect("Sender must be a fighter.");
+        let fighter = &mut royale.fighters[index];
+        assert!(fighter.is_alive(), "Fighter is already eliminated.");
+        assert!(fighter.secret.is_none(), "Secret already revealed.");
+        assert!(
+            Base64VecU8(env::sha256(&secret.0)) == fighter.commitment,
+            "Secret does not match commitment."
+        );
+        fighter.secret = Some(secret);
+
+        // The first turn gets a full timeout once the last secret is in
+        if royale.is_revealed() {
+            royale.extend_deadline();
+        }
+
+        env::log_str(&format!(
+            "Secret revealed by {} for royale {}.",
+            sender, royale_id.0
+        ));
+    }
+
+    #[payable]
+    pub fn take_royale_turn(
+        &mut self,
+        royale_id: U128,
+        target: AccountId,
+        style: RoastStyle,
+    ) -> PromiseOrValue<u8> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let mut royale = self
+            .royales
+            .get(&royale_id.0)
+            .expect("Royale not found.")
+            .clone();
+        assert!(royale.start_time.is_some(), "Royale has not started yet.");
+        assert!(royale.winner.is_none(), "Royale already completed.");
+        assert!(
+            royale.is_revealed(),
+            "All fighters must reveal their secrets first."
+        );
+
+        let attacker = royale.current();
+        assert!(
+            royale.fighters[attacker].account_id == sender,
+            "Invalid sender."
+        );
+        let target = royale
+            .fighter(&target)
+            .expect("Target is not in the royale.");
+        assert!(target != attacker, "Cannot roast yourself.");
+        assert!(
+            royale.fighters[target].is_alive(),
+            "Target is already eliminated."
+        );
+
+        let turn = royale.turns.len();
+        let seed = env::random_seed();
+        let roll = royale.roll(turn, &seed);
+        let hit = royale.damage(attacker, target, style, &roll);
+
+        let fighter = &mut royale.fighters[target];
+        fighter.hp = fighter.hp.saturating_sub(hit.damage);
+        let eliminated = royale.is_knocked_out(&royale.fighters[target]);
+        if eliminated {
+            royale.fighters[target].eliminated_at = Some(turn as u32);
+            env::log_str(&format!(
+                "{} was eliminated from royale {}.",
+                royale.fighters[target].account_id, royale_id.0
+            ));
+        }
+
+        royale.turns.push(RoyaleTurn {
+            creation_time: env::block_timestamp(),
+            attacker: sender,
+            target: royale.fighters[target].account_id.clone(),
+            figure: royale.fighters[attacker].figure.clone(),
+            target_figure: royale.fighters[target].figure.clone(),
+            base_stat: hit.base_stat,
+            max_damage: hit.max_damage,
+            roll: hit.roll,
+            bonus: hit.bonus,
+            penalty: hit.penalty,
+            damage: hit.damage,
+            style,
+            critical: hit.critical,
+            dodged: hit.dodged,
+            eliminated,
+            seed: Base64VecU8(seed),
+            roast_cid: None,
+        });
+
+        if royale.survivors().count() == 1 {
+            return PromiseOrValue::Promise(self.settle_royale(royale));
+        }
+
+        royale.extend_deadline();
+        self.royales.insert(royale_id.0, royale);
+        PromiseOrValue::Value(hit.damage)
+    }
+
+    // Once the deadline passes, any fighter still standing can eliminate whoever is holding up
+    // the royale: every fighter yet to reveal a secret, or else the fighter to move. Play then
+    // moves on to the next survivor.
+    #[payable]
+    pub fn claim_royale_timeout(&mut self, royale_id: U128) -> Option<Promise> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        let mut royale = self
+            .royales
+            .get(&royale_id.0)
+            .expect("Royale not found.")
+            .clone();
+        assert!(royale.winner.is_none(), "Royale already completed.");
+        let deadline = royale.deadline.expect("Royale has not started yet.");
+        assert!(
+            env::block_timestamp() >= deadline,
+            "The deadline has not passed yet."
+        );
+
+        let idle: Vec<usize> = if royale.is_revealed() {
+            vec![royale.current()]
+        } else {
+            (0..royale.fighters.len())
+                .filter(|&i| royale.fighters[i].is_alive() && royale.fighters[i].secret.is_none())
+                .collect()
+        };
+        let claimant = royale.fighter(&sender).expect("Sender must be a fighter.");
+        // Nobody revealed, so nobody is owed a win and the royale is called off
+        if idle.len() == royale.survivors().count() {
+            self.royales.remove(&royale_id.0);
+            self.refund_royale(royale);
+            return None;
+        }
+        assert!(
+            royale.fighters[claimant].is_alive() && !idle.contains(&claimant),
+            "Only a fighter who is not overdue can claim a timeout."
+        );
+
+        let turns = royale.turns.len() as u32;
+        for i in idle {
+            royale.fighters[i].eliminated_at = Some(turns);
+            env::log_str(&format!(
+                "{} timed out of royale {}.",
+                royale.fighters[i].account_id, royale_id.0
+            ));
+        }
+
+        if royale.survivors().count() == 1 {
+            return Some(self.settle_royale(royale));
+        }
+
+        royale.extend_deadline();
+        self.royales.insert(royale_id.0, royale);
+        None
+    }
+
+    pub fn set_royale_roast(&mut self, royale_id: U128, turn: usize, roast_cid: String) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let royale = self
+            .royales
+            .get_mut(&royale_id.0)
+            .expect("Royale not found.");
+        assert!(turn < royale.turns.len(), "Turn has not been taken.");
+        assert!(royale.turns[turn].roast_cid.is_none(), "Roast already set.");
+
+        royale.turns[turn].roast_cid = Some(roast_cid);
+    }
+
+    // Stakes go back to each fighter's balance
+    fn refund_royale(&mut self, royale: Royale) {
+        for fighter in royale.fighters {
+            *self
+                .stakes
+                .entry((fighter.account_id, royale.token.clone()))
+                .or_insert(0) += royale.stake.0;
+        }
+
+        env::log_str(&format!(
+            "Royale {} canceled. Stakes refunded.",
+            royale.id.0
+        ));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn _create_royale(
+        &mut self,
+        sender: AccountId,
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+        token: AccountId,
+        stake: U128,
+        max_players: u8,
+        knockout_percent: Option<u8>,
+    ) -> U128 {
+        assert!(
+            (MIN_ROYALE_PLAYERS..=MAX_ROYALE_PLAYERS).contains(&max_players),
+            "Royales must have between {} and {} players.",
+            MIN_ROYALE_PLAYERS,
+            MAX_ROYALE_PLAYERS
+        );
+        let knockout_percent = knockout_percent.unwrap_or(0);
+        assert!(
+            knockout_percent <= MAX_KNOCKOUT_PERCENT,
+            "Knockout threshold must be at most {}%.",
+            MAX_KNOCKOUT_PERCENT
+        );
+
+        self.assert_token(&token);
+        let royale_id = self.next_royale_id;
+        assert!(
+            stake.0 >= self.tokens[&token].min_stake.0,
+            "Minimum stake required."
+        );
+
+        let royale = Royale::new(
+            royale_id,
+            stake,
+            token,
+            &self.config,
+            max_players,
+            knockout_percent,
+        );
+        self.royales.insert(royale_id, royale);
+        self.next_royale_id += 1;
+
+        env::log_str(&format!("Royale {} created by {}.", royale_id, sender));
+        self._join_royale(sender, U128(royale_id), figure, secret_hash);
+        U128(royale_id)
+    }
+
+    pub(crate) fn _join_royale(
+        &mut self,
+        sender: AccountId,
+        royale_id: U128,
+        figure: FigureId,
+        secret_hash: Base64VecU8,
+    ) {
+        self.assert_figure(&figure);
+        assert!(secret_hash.0.len() == 32, "Secret hash must be 32 bytes.");
+        let mut royale = self
+            .royales
+            .get(&royale_id.0)
+            .expect("Royale not found.")
+            .clone();
+        assert!(royale.start_time.is_none(), "Royale already started.");
+        assert!(
+            royale.fighter(&sender).is_none(),
+            "Account is already participating."
+        );
+        assert!(
+            !royale
+                .fighters
+                .iter()
+                .any(|fighter| fighter.figure == figure),
+            "Historical figure already selected."
+        );
+
+        let balance = self
+            .stakes
+            .entry((sender.clone(), royale.token.clone()))
+            .or_insert(0);
+        assert!(*balance >= royale.stake.0, "Insufficient balance");
+        *balance -= royale.stake.0;
+
+        royale.fighters.push(Fighter {
+            account_id: sender.clone(),
+            figure,
+            version: 0,
+            base_stats: Stats::default(),
+            level: 0,
+            max_hp: 0,
+            hp: 0,
+            commitment: secret_hash,
+            secret: None,
+            eliminated_at: None,
+        });
+        env::log_str(&format!("{} joined royale {}.", sender, royale_id.0));
+
+        if royale.fighters.len() == royale.max_players as usize {
+            self.start_royale(&mut royale);
+        }
+        self.royales.insert(royale_id.0, royale);
+    }
+
+    // Snapshots every fighter's figure stats and levels as the fight begins
+    fn start_royale(&self, royale: &mut Royale) {
+        for fighter in royale.fighters.iter_mut() {
+            let loadout = self.loadout(&fighter.account_id, &fighter.figure);
+            fighter.version = loadout.version;
+            fighter.base_stats = loadout.stats;
+            fighter.level = loadout.level;
+            fighter.max_hp = fighter.stats().hit_points();
+            fighter.hp = fighter.max_hp;
+        }
+        royale.start_time = Some(env::block_timestamp());
+        // Every fighter has one timeout to reveal their secret
+        royale.extend_deadline();
+
+        env::log_str(&format!("Royale {} started.", royale.id.0));
+    }
+
+    fn settle_royale(&mut self, mut royale: Royale) -> Promise {
+        let winner = royale.survivors().next().unwrap().account_id.clone();
+        royale.winner = Some(winner.clone());
+        env::log_str(&format!(
+            "Royale {} finished! Result: {} won!",
+            royale.id.0, winner
+        ));
+
+        let pot = royale.pot();
+        let fee = pot * royale.fee_percent as u128 / 100;
+        let payout = pot - fee;
+        // Winnings and losses are tracked in $ROASTI only
+        let (stake, won) = if royale.token == self.ft_contract {
+            (royale.stake.0, payout)
+        } else {
+            (0, 0)
+        };
+
+        for fighter in royale.fighters.iter() {
+            let result = if fighter.account_id == winner {
+                Winner::PlayerA
+            } else {
+                Winner::PlayerB
+            };
+            let (dealt, taken) = royale.damage_by(&fighter.account_id);
+            self.record_result(&fighter.account_id, result, dealt, taken, stake, won);
+            self.gain_xp(&fighter.account_id, &fighter.figure, result);
+        }
+
+        self.burn(&royale.token, U128(fee));
+        let promise = self.transfer(&royale.token, winner, U128(payout));
+        self.royales.insert(royale.id.0, royale);
+        promise
+    }
+}
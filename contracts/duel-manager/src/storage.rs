@@ -1,6 +1,21 @@
-use crate::figures::HistoricalFigure;
-use near_sdk::json_types::U128;
-use near_sdk::{near, AccountId};
+use crate::figures::{FigureId, MoveEffect, SignatureMove, Stats};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{env, near, AccountId};
+use std::cmp;
+
+pub const DEFAULT_TURNS: u8 = 10;
+const DEFAULT_FEE_PERCENT: u8 = 10;
+const DEFAULT_ACCEPT_TIMEOUT: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_TURN_TIMEOUT: u64 = 48 * 60 * 60 * 1_000_000_000;
+const DEFAULT_TOP_DUEL_WINDOW: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_REVEAL_TIMEOUT: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_DRAFT_TIMEOUT: u64 = 12 * 60 * 60 * 1_000_000_000;
+// Percent chance per point of the attacker's highest stat
+const CRITICAL_CHANCE_PER_STAT: u8 = 2;
+// Percent chance per point of the defender's strategy
+const DODGE_CHANCE_PER_STAT: u8 = 1;
+// Bytes of a roll used for extra dice. Bytes 0, 2 and 3 roll the dice, criticals and dodges.
+const REROLL_BYTES: [usize; 2] = [1, 4];
 
 #[near(serializers = [json, borsh])]
 #[derive(Copy, Clone)]
@@ -12,27 +27,151 @@ pub enum RoastStyle {
 }
 
 impl RoastStyle {
-    pub fn is_strong_against(&self, other: RoastStyle) -> bool {
-        match (self, other) {
-            (RoastStyle::Witty, RoastStyle::Brutal) => true,
-            (RoastStyle::Brutal, RoastStyle::Strategic) => true,
-            (RoastStyle::Strategic, RoastStyle::Mocking) => true,
-            (RoastStyle::Mocking, RoastStyle::Witty) => true,
-            _ => false,
+    pub const ALL: [RoastStyle; 4] = [
+        RoastStyle::Witty,
+        RoastStyle::Brutal,
+        RoastStyle::Strategic,
+        RoastStyle::Mocking,
+    ];
+
+    // The stat a roast in this style rolls against
+    pub fn stat(&self, stats: &Stats) -> u8 {
+        match self {
+            RoastStyle::Witty => stats.wit,
+            RoastStyle::Brutal => stats.brutality,
+            RoastStyle::Strategic => stats.strategy,
+            RoastStyle::Mocking => stats.mockery,
         }
     }
 
+    pub fn counter(other: RoastStyle) -> RoastStyle {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.is_strong_against(other))
+            .unwrap()
+    }
+
+    pub fn is_strong_against(&self, other: RoastStyle) -> bool {
+        matches!(
+            (self, other),
+            (RoastStyle::Witty, RoastStyle::Brutal)
+                | (RoastStyle::Brutal, RoastStyle::Strategic)
+                | (RoastStyle::Strategic, RoastStyle::Mocking)
+                | (RoastStyle::Mocking, RoastStyle::Witty)
+        )
+    }
+
     pub fn is_weak_against(&self, other: RoastStyle) -> bool {
-        match (self, other) {
-            (RoastStyle::Witty, RoastStyle::Mocking) => true,
-            (RoastStyle::Brutal, RoastStyle::Witty) => true,
-            (RoastStyle::Strategic, RoastStyle::Brutal) => true,
-            (RoastStyle::Mocking, RoastStyle::Strategic) => true,
-            _ => false,
+        matches!(
+            (self, other),
+            (RoastStyle::Witty, RoastStyle::Mocking)
+                | (RoastStyle::Brutal, RoastStyle::Witty)
+                | (RoastStyle::Strategic, RoastStyle::Brutal)
+                | (RoastStyle::Mocking, RoastStyle::Strategic)
+        )
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone, Default)]
+pub enum DuelMode {
+    #[default]
+    Damage,
+    Knockout,
+}
+
+// A whitelisted token. The minimum stake is in the token's own units.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Token {
+    pub decimals: u8,
+    pub min_stake: U128,
+}
+
+impl Token {
+    // Starts with a minimum stake of one whole token
+    pub fn new(decimals: u8) -> Self {
+        Self {
+            decimals,
+            min_stake: U128(10u128.pow(decimals as u32)),
         }
     }
 }
 
+// Economic terms set by the admin. Duels and royales copy the fee and timeouts when they are
+// created, so later changes only apply to new games.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Config {
+    pub fee_percent: u8,
+    // How long a duel waits for an opponent before its creator can cancel it
+    pub accept_timeout: u64,
+    // For duels that don't set their own
+    pub turn_timeout: u64,
+    // How recently the top duel must have been played
+    pub top_duel_window: u64,
+    // How long a side has to reveal a blind pick once the duel begins
+    pub reveal_timeout: u64,
+    // How long each draft step may take
+    pub draft_timeout: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            fee_percent: DEFAULT_FEE_PERCENT,
+            accept_timeout: DEFAULT_ACCEPT_TIMEOUT,
+            turn_timeout: DEFAULT_TURN_TIMEOUT,
+            top_duel_window: DEFAULT_TOP_DUEL_WINDOW,
+            reveal_timeout: DEFAULT_REVEAL_TIMEOUT,
+            draft_timeout: DEFAULT_DRAFT_TIMEOUT,
+        }
+    }
+}
+
+// A chess clock per side, in nanoseconds. The increment is added after every move.
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone)]
+pub struct TimeControl {
+    pub base: u64,
+    pub increment: u64,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+#[serde(default)]
+pub struct DuelOptions {
+    pub mode: DuelMode,
+    pub turns: Option<u8>,
+    pub rounds: Option<u8>,
+    pub require_cards: bool,
+    pub blind_pick: bool,
+    pub draft: bool,
+    // Bans per player in a draft
+    pub bans: Option<u8>,
+    // Two players and two figures per side
+    pub team: bool,
+    // Nanoseconds each player has for a secret or a turn before the opponent can claim the win
+    pub turn_timeout: Option<u64>,
+    pub time_control: Option<TimeControl>,
+    // Any whitelisted token. Defaults to $ROASTI.
+    pub token: Option<AccountId>,
+}
+
+impl DuelOptions {
+    pub fn turns(&self) -> u8 {
+        self.turns.unwrap_or(DEFAULT_TURNS)
+    }
+
+    pub fn bans(&self) -> u8 {
+        self.bans.unwrap_or(1)
+    }
+
+    pub fn rounds(&self) -> u8 {
+        self.rounds.unwrap_or(1)
+    }
+}
+
 #[near(serializers = [json, borsh])]
 #[derive(Copy, Clone)]
 pub enum Winner {
@@ -41,12 +180,225 @@ pub enum Winner {
     Draw,
 }
 
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone)]
+pub enum WinReason {
+    // The rounds were played out
+    Decision,
+    // The opponent missed the deadline to reveal or draft their figure
+    Forfeit,
+    // The opponent missed the deadline for a secret or a turn. Stalled bracket duels where both
+    // players missed it are settled as a draw for this reason too.
+    Timeout,
+    // The opponent gave up
+    Surrender,
+    // The opponent ran out of time on their clock
+    FlagFall,
+}
+
+// A figure choice: in the open, as sha256(len || figure id || salt) for blind picks, or left
+// to the draft
+pub enum Pick {
+    Open(FigureId),
+    Blind(Base64VecU8),
+    Draft,
+}
+
+impl Pick {
+    pub fn new(figure: Option<FigureId>, figure_hash: Option<Base64VecU8>) -> Self {
+        match (figure, figure_hash) {
+            (Some(figure), None) => Pick::Open(figure),
+            (None, Some(figure_hash)) => Pick::Blind(figure_hash),
+            (None, None) => Pick::Draft,
+            _ => env::panic_str("Provide either a figure or a figure hash, not both."),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum DraftStep {
+    Ban,
+    Pick,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Side {
+    PlayerA,
+    PlayerB,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Bet {
+    pub account_id: AccountId,
+    pub duel_id: U128,
+    pub side: Side,
+    pub amount: U128,
+    pub creation_time: u64,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone, PartialEq)]
+pub enum BetResult {
+    Won(Side),
+    // A draw, a canceled duel or a pool with bets on one side only
+    Refunded,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct BetPool {
+    pub token: AccountId,
+    pub pool_a: U128,
+    pub pool_b: U128,
+    // Bets on each side that haven't been claimed yet
+    pub bettors_a: u32,
+    pub bettors_b: u32,
+    pub result: Option<BetResult>,
+    // What the winning side shares after the fee, and how much of it has been claimed
+    pub payable: U128,
+    pub paid: U128,
+}
+
+impl BetPool {
+    pub fn new(token: AccountId) -> Self {
+        Self {
+            token,
+            pool_a: U128(0),
+            pool_b: U128(0),
+            bettors_a: 0,
+            bettors_b: 0,
+            result: None,
+            payable: U128(0),
+            paid: U128(0),
+        }
+    }
+
+    pub fn bettors(&self) -> u32 {
+        self.bettors_a + self.bettors_b
+    }
+
+    pub fn side_bettors(&mut self, side: Side) -> &mut u32 {
+        match side {
+            Side::PlayerA => &mut self.bettors_a,
+            Side::PlayerB => &mut self.bettors_b,
+        }
+    }
+
+    pub fn side_pool(&mut self, side: Side) -> &mut U128 {
+        match side {
+            Side::PlayerA => &mut self.pool_a,
+            Side::PlayerB => &mut self.pool_b,
+        }
+    }
+
+    pub fn total(&self) -> u128 {
+        self.pool_a.0 + self.pool_b.0
+    }
+
+    pub fn side_total(&self, side: Side) -> u128 {
+        match side {
+            Side::PlayerA => self.pool_a.0,
+            Side::PlayerB => self.pool_b.0,
+        }
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DuelStatus {
+    Pending,
+    Challenged,
+    Active,
+    Finished,
+}
+
+impl Winner {
+    // The same result seen from player B's side
+    pub fn flipped(self) -> Winner {
+        match self {
+            Winner::PlayerA => Winner::PlayerB,
+            Winner::PlayerB => Winner::PlayerA,
+            Winner::Draw => Winner::Draw,
+        }
+    }
+}
+
+// Funds the house stakes in duels against the contract. House stakes leave the balance while
+// their duel runs and count towards the exposure.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+pub struct Bankroll {
+    pub balance: U128,
+    pub exposure: U128,
+    pub max_stake: U128,
+    pub max_exposure: U128,
+    pub won: U128,
+    pub lost: U128,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+pub struct PlayerStats {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub roasti_won: U128,
+    pub roasti_lost: U128,
+}
+
+// A figure as a player fields it: its current stat version and the player's level with it
+pub struct Loadout {
+    pub version: u32,
+    pub stats: Stats,
+    pub level: u8,
+}
+
+// The final damage is always `roll + bonus - penalty`
+pub struct Hit {
+    pub base_stat: u8,
+    pub max_damage: u8,
+    pub roll: u8,
+    pub bonus: u8,
+    pub penalty: u8,
+    pub damage: u8,
+    pub critical: bool,
+    pub dodged: bool,
+}
+
+// The second player on a team duel side. Stats are snapshotted as the duel begins.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Partner {
+    pub account_id: AccountId,
+    pub figure: FigureId,
+    pub version: u32,
+    pub base_stats: Stats,
+    pub level: u8,
+}
+
 #[near(serializers = [json, borsh])]
 #[derive(Clone)]
 pub struct Turn {
     pub creation_time: u64,
+    pub attacker: AccountId,
+    pub defender: AccountId,
+    pub figure: FigureId,
+    pub base_stat: u8,
+    pub max_damage: u8,
+    pub roll: u8,
+    pub bonus: u8,
+    pub penalty: u8,
     pub damage: u8,
     pub style: RoastStyle,
+    pub signature: Option<SignatureMove>,
+    pub critical: bool,
+    pub dodged: bool,
+    pub round: u8,
+    pub seed: Base64VecU8,
     pub roast_cid: Option<String>,
 }
 
@@ -57,10 +409,874 @@ pub struct Duel {
     pub creation_time: u64,
     pub start_time: Option<u64>,
     pub stake: U128,
+    pub token: AccountId,
+    pub fee_percent: u8,
+    pub accept_timeout: u64,
+    pub mode: DuelMode,
+    pub turns_per_round: u8,
+    pub rounds: u8,
+    pub require_cards: bool,
+    pub blind_pick: bool,
+    pub draft: bool,
+    pub bans_per_player: u8,
+    pub bans: Vec<FigureId>,
+    pub draft_deadline: Option<u64>,
+    pub draft_timeout: u64,
+    pub reveal_timeout: u64,
+    pub turn_timeout: u64,
+    // When whoever owes the next secret or turn runs out of time. Set once both figures are in.
+    pub deadline: Option<u64>,
+    pub time_control: Option<TimeControl>,
+    // Time left on each side's clock as of `clock_start`, when the side to move began thinking
+    pub clock_a: Option<u64>,
+    pub clock_b: Option<u64>,
+    pub clock_start: Option<u64>,
+    pub team: bool,
+    // The contract itself plays `player_b`, staking from the bankroll
+    pub house: bool,
     pub player_a: AccountId,
-    pub figure_a: HistoricalFigure,
+    pub figure_a: Option<FigureId>,
+    pub figure_commitment_a: Option<Base64VecU8>,
+    pub version_a: u32,
+    pub base_stats_a: Stats,
+    pub level_a: u8,
+    pub partner_a: Option<Partner>,
     pub player_b: Option<AccountId>,
-    pub figure_b: Option<HistoricalFigure>,
+    pub figure_b: Option<FigureId>,
+    pub figure_commitment_b: Option<Base64VecU8>,
+    pub version_b: u32,
+    pub base_stats_b: Stats,
+    pub level_b: u8,
+    pub partner_b: Option<Partner>,
+    pub opponent: Option<AccountId>,
+    pub tournament_id: Option<U128>,
+    pub commitment_a: Option<Base64VecU8>,
+    pub commitment_b: Option<Base64VecU8>,
+    pub secret_a: Option<Base64VecU8>,
+    pub secret_b: Option<Base64VecU8>,
+    pub hp_a: Option<u8>,
+    pub hp_b: Option<u8>,
     pub turns: Vec<Turn>,
+    pub round_results: Vec<Winner>,
     pub winner: Option<Winner>,
+    pub win_reason: Option<WinReason>,
+}
+
+impl Duel {
+    pub fn new(
+        id: u128,
+        stake: U128,
+        token: AccountId,
+        player_a: AccountId,
+        pick: Pick,
+        options: &DuelOptions,
+        config: &Config,
+    ) -> Self {
+        let (figure_a, figure_commitment_a) = match pick {
+            Pick::Open(figure) => (Some(figure), None),
+            Pick::Blind(figure_hash) => (None, Some(figure_hash)),
+            Pick::Draft => (None, None),
+        };
+        Self {
+            id: U128(id),
+            creation_time: env::block_timestamp(),
+            start_time: None,
+            stake,
+            token,
+            fee_percent: config.fee_percent,
+            accept_timeout: config.accept_timeout,
+            mode: options.mode,
+            turns_per_round: options.turns(),
+            rounds: options.rounds(),
+            require_cards: options.require_cards,
+            blind_pick: options.blind_pick,
+            draft: options.draft,
+            bans_per_player: options.bans(),
+            bans: Vec::new(),
+            draft_deadline: None,
+            draft_timeout: config.draft_timeout,
+            reveal_timeout: config.reveal_timeout,
+            turn_timeout: options.turn_timeout.unwrap_or(config.turn_timeout),
+            deadline: None,
+            time_control: options.time_control,
+            clock_a: options.time_control.map(|time_control| time_control.base),
+            clock_b: options.time_control.map(|time_control| time_control.base),
+            clock_start: None,
+            team: options.team,
+            house: false,
+            player_a,
+            figure_a,
+            figure_commitment_a,
+            version_a: 0,
+            base_stats_a: Stats::default(),
+            level_a: 0,
+            partner_a: None,
+            player_b: None,
+            figure_b: None,
+            figure_commitment_b: None,
+            version_b: 0,
+            base_stats_b: Stats::default(),
+            level_b: 0,
+            partner_b: None,
+            opponent: None,
+            tournament_id: None,
+            commitment_a: None,
+            commitment_b: None,
+            secret_a: None,
+            secret_b: None,
+            hp_a: None,
+            hp_b: None,
+            turns: Vec::new(),
+            round_results: Vec::new(),
+            winner: None,
+            win_reason: None,
+        }
+    }
+
+    pub fn status(&self) -> DuelStatus {
+        if self.winner.is_some() {
+            DuelStatus::Finished
+        } else if self.start_time.is_some() {
+            DuelStatus::Active
+        } else if self.opponent.is_some() {
+            DuelStatus::Challenged
+        } else {
+            DuelStatus::Pending
+        }
+    }
+
+    // Everyone who should see the duel in their account listing
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountId> {
+        self.players().chain(self.opponent.as_ref())
+    }
+
+    // Everyone who joined and staked
+    pub fn players(&self) -> impl Iterator<Item = &AccountId> {
+        std::iter::once(&self.player_a)
+            .chain(self.player_b.as_ref())
+            .chain(self.partner_a.as_ref().map(|partner| &partner.account_id))
+            .chain(self.partner_b.as_ref().map(|partner| &partner.account_id))
+    }
+
+    pub fn figures(&self) -> impl Iterator<Item = &FigureId> {
+        self.figure_a
+            .iter()
+            .chain(self.figure_b.as_ref())
+            .chain(self.partner_a.as_ref().map(|partner| &partner.figure))
+            .chain(self.partner_b.as_ref().map(|partner| &partner.figure))
+    }
+
+    pub fn side(&self, player_a: bool) -> Vec<&AccountId> {
+        let (player, partner) = if player_a {
+            (Some(&self.player_a), &self.partner_a)
+        } else {
+            (self.player_b.as_ref(), &self.partner_b)
+        };
+        player
+            .into_iter()
+            .chain(partner.as_ref().map(|partner| &partner.account_id))
+            .collect()
+    }
+
+    pub fn pot(&self) -> u128 {
+        self.stake.0 * self.players().count() as u128
+    }
+
+    pub fn fee(&self) -> u128 {
+        self.pot() * self.fee_percent as u128 / 100
+    }
+
+    // What each winner takes home after the fee. Team winners split the pot.
+    pub fn payout_share(&self) -> u128 {
+        let winners = self.players().count() as u128 / 2;
+        (self.pot() - self.fee()) / winners
+    }
+
+    // Team duels begin once both sides have two players
+    pub fn is_full(&self) -> bool {
+        self.player_b.is_some()
+            && (!self.team || (self.partner_a.is_some() && self.partner_b.is_some()))
+    }
+
+    // Team turns rotate A, B, partner A, partner B, and each attacks the opponent in the same slot
+    pub fn is_partner_turn(&self, turn: usize) -> bool {
+        self.team && ((turn - self.round_start(turn)) / 2) % 2 == 1
+    }
+
+    pub fn member(&self, player_a: bool, partner: bool) -> (&AccountId, &FigureId) {
+        match (player_a, partner) {
+            (true, false) => (&self.player_a, self.figure_a.as_ref().unwrap()),
+            (false, false) => (
+                self.player_b.as_ref().unwrap(),
+                self.figure_b.as_ref().unwrap(),
+            ),
+            (player_a, true) => {
+                let partner = self.partner(player_a).unwrap();
+                (&partner.account_id, &partner.figure)
+            }
+        }
+    }
+
+    fn partner(&self, player_a: bool) -> Option<&Partner> {
+        if player_a {
+            self.partner_a.as_ref()
+        } else {
+            self.partner_b.as_ref()
+        }
+    }
+
+    pub fn is_committed(&self) -> bool {
+        self.commitment_a.is_some() && self.commitment_b.is_some()
+    }
+
+    // Bans alternate starting with player A, then player A picks before player B.
+    // Returns the next step and whether it's player A's, or None outside of a running draft.
+    pub fn draft_step(&self) -> Option<(DraftStep, bool)> {
+        if !self.draft || self.player_b.is_none() {
+            return None;
+        }
+
+        if self.bans.len() < self.bans_per_player as usize * 2 {
+            return Some((DraftStep::Ban, self.bans.len() % 2 == 0));
+        }
+        match (&self.figure_a, &self.figure_b) {
+            (None, _) => Some((DraftStep::Pick, true)),
+            (Some(_), None) => Some((DraftStep::Pick, false)),
+            _ => None,
+        }
+    }
+
+    pub fn figures_revealed(&self) -> bool {
+        self.figure_a.is_some() && self.figure_b.is_some()
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.secret_a.is_some() && self.secret_b.is_some()
+    }
+
+    // Mixes both revealed secrets with the block seed, so no roll can be known before both
+    // players have committed. Once revealed the secrets are public, though, so each roll is only
+    // as unpredictable as the seed of the block the turn lands in. A player who can predict or
+    // influence that seed, such as a validator, can time their turn for a better roll.
+    // Each byte of the hash is an independent roll
+    pub fn roll(&self, turn: usize, seed: &[u8]) -> Vec<u8> {
+        let secret_a = self.secret_a.as_ref().expect("Secrets not revealed.");
+        let secret_b = self.secret_b.as_ref().expect("Secrets not revealed.");
+
+        let mut input = Vec::with_capacity(secret_a.0.len() + secret_b.0.len() + seed.len() + 8);
+        input.extend_from_slice(&secret_a.0);
+        input.extend_from_slice(&secret_b.0);
+        input.extend_from_slice(seed);
+        input.extend_from_slice(&(turn as u64).to_le_bytes());
+
+        env::sha256(&input)
+    }
+
+    // Index of the first turn in the round that `turn` belongs to
+    fn round_start(&self, turn: usize) -> usize {
+        let round = self
+            .turns
+            .get(turn)
+            .map_or(self.round_results.len() as u8, |t| t.round);
+        self.turns
+            .iter()
+            .position(|t| t.round == round)
+            .unwrap_or(turn)
+    }
+
+    pub fn is_player_a_turn(&self, turn: usize) -> bool {
+        (turn - self.round_start(turn)) % 2 == 0
+    }
+
+    pub fn damage(
+        &self,
+        turn: usize,
+        style: RoastStyle,
+        signature: Option<SignatureMove>,
+        roll: &[u8],
+    ) -> Hit {
+        let last_turn = if turn > self.round_start(turn) {
+            Some(&self.turns[turn - 1])
+        } else {
+            None
+        };
+        let effect = signature.map_or_else(MoveEffect::default, SignatureMove::effect);
+        let read = last_turn
+            .and_then(|t| t.signature)
+            .is_some_and(|signature| signature.effect().reads);
+        let strong = last_turn.is_some_and(|t| style.is_strong_against(t.style)) && !read;
+        let weak = last_turn.is_some_and(|t| style.is_weak_against(t.style)) || read;
+
+        // Roll damage based on the chosen roast style
+        let is_player_a = self.is_player_a_turn(turn);
+        let partner = self.is_partner_turn(turn);
+        let stats = self.stats(is_player_a, partner);
+        let stat_damage = if effect.highest_stat {
+            stats.highest()
+        } else {
+            style.stat(&stats)
+        };
+        let boost = if strong {
+            cmp::max(effect.boost, 5)
+        } else {
+            effect.boost
+        };
+        let max_damage = stat_damage + boost;
+
+        let mut dice = (roll[0] % max_damage) + 1; // Roll between 1 and max damage
+        for &byte in &REROLL_BYTES[..effect.rerolls as usize] {
+            dice = cmp::max(dice, (roll[byte] % max_damage) + 1);
+        }
+        let floor = (max_damage as u16 * effect.floor_percent as u16).div_ceil(100) as u8;
+        dice = cmp::max(dice, floor);
+
+        let mut damage = dice;
+        let (mut bonus, mut penalty) = (0, 0);
+        if weak && !effect.ignores_weakness {
+            let weakened = cmp::max(damage.saturating_sub(5), 1);
+            penalty += damage - weakened;
+            damage = weakened;
+        }
+        let payback = last_turn.map_or(0, |last_turn| {
+            (last_turn.damage as u16 * effect.payback_percent as u16 / 100) as u8
+        });
+        let boosted = damage.saturating_add(payback).saturating_add(effect.bonus);
+        bonus += boosted - damage;
+        damage = boosted;
+
+        // A dodge takes precedence over a critical hit
+        let defense = self.stats(!is_player_a, partner);
+        let dodged =
+            !effect.undodgeable && roll[3] % 100 < defense.strategy * DODGE_CHANCE_PER_STAT;
+        let critical = !dodged
+            && effect
+                .critical
+                .unwrap_or_else(|| roll[2] % 100 < stats.highest() * CRITICAL_CHANCE_PER_STAT);
+        if dodged {
+            penalty += damage;
+            damage = 0;
+        } else if critical {
+            let doubled = damage.saturating_mul(2);
+            bonus += doubled - damage;
+            damage = doubled;
+        }
+
+        Hit {
+            base_stat: stat_damage,
+            max_damage,
+            roll: dice,
+            bonus,
+            penalty,
+            damage,
+            critical,
+            dodged,
+        }
+    }
+
+    // Clocks start running once turns can be taken
+    pub fn start_clock(&mut self) {
+        if self.time_control.is_some() && self.is_revealed() && self.figures_revealed() {
+            self.clock_start = Some(env::block_timestamp());
+        }
+    }
+
+    // Charges the time since the clock started to the side that just moved
+    pub fn punch_clock(&mut self, player_a: bool) {
+        let (Some(time_control), Some(clock_start)) = (self.time_control, self.clock_start) else {
+            return;
+        };
+        let now = env::block_timestamp();
+        let clock = if player_a {
+            self.clock_a.as_mut().unwrap()
+        } else {
+            self.clock_b.as_mut().unwrap()
+        };
+        let elapsed = now - clock_start;
+        assert!(elapsed <= *clock, "Out of time.");
+
+        *clock = *clock - elapsed + time_control.increment;
+        self.clock_start = Some(now);
+    }
+
+    // The side to move, if its clock has run out
+    pub fn flag_fallen(&self) -> Option<bool> {
+        let clock_start = self.clock_start?;
+        let player_a = self.is_player_a_turn(self.turns.len());
+        let clock = if player_a { self.clock_a } else { self.clock_b }?;
+        (env::block_timestamp() - clock_start > clock).then_some(player_a)
+    }
+
+    // Both clocks as of now, with the time the side to move has spent so far taken off theirs
+    pub fn live_clocks(&self) -> Option<(u64, u64)> {
+        let (clock_a, clock_b) = (self.clock_a?, self.clock_b?);
+        let Some(clock_start) = self.clock_start.filter(|_| self.winner.is_none()) else {
+            return Some((clock_a, clock_b));
+        };
+        let elapsed = env::block_timestamp().saturating_sub(clock_start);
+        if self.is_player_a_turn(self.turns.len()) {
+            Some((clock_a.saturating_sub(elapsed), clock_b))
+        } else {
+            Some((clock_a, clock_b.saturating_sub(elapsed)))
+        }
+    }
+
+    pub fn extend_deadline(&mut self) {
+        self.deadline = Some(env::block_timestamp() + self.turn_timeout);
+    }
+
+    // Which side owes the next move once the figures are in: a secret to commit or reveal,
+    // then a turn. None while both sides still owe the same step of their secret.
+    pub fn waiting_on(&self) -> Option<bool> {
+        let progress = |commitment: &Option<Base64VecU8>, secret: &Option<Base64VecU8>| {
+            commitment.is_some() as u8 + secret.is_some() as u8
+        };
+        let progress_a = progress(&self.commitment_a, &self.secret_a);
+        let progress_b = progress(&self.commitment_b, &self.secret_b);
+        match progress_a.cmp(&progress_b) {
+            cmp::Ordering::Less => Some(true),
+            cmp::Ordering::Greater => Some(false),
+            cmp::Ordering::Equal if progress_a == 2 => {
+                Some(self.is_player_a_turn(self.turns.len()))
+            }
+            cmp::Ordering::Equal => None,
+        }
+    }
+
+    pub fn is_house(&self, account_id: &AccountId) -> bool {
+        self.house && *account_id == env::current_account_id()
+    }
+
+    pub fn has_used_signature(&self, account_id: &AccountId) -> bool {
+        self.turns
+            .iter()
+            .any(|turn| turn.signature.is_some() && turn.attacker == *account_id)
+    }
+
+    pub fn start(&mut self, player_b: AccountId) {
+        self.start_time = Some(env::block_timestamp());
+        self.player_b = Some(player_b);
+    }
+
+    // Stats and levels are fixed for the whole duel, so later rebalances or experience gained
+    // elsewhere don't change its rolls
+    pub fn load(&mut self, a: &Loadout, b: &Loadout) {
+        (self.version_a, self.base_stats_a, self.level_a) = (a.version, a.stats, a.level);
+        (self.version_b, self.base_stats_b, self.level_b) = (b.version, b.stats, b.level);
+        self.reset_hit_points();
+    }
+
+    pub fn stats(&self, player_a: bool, partner: bool) -> Stats {
+        match (player_a, partner) {
+            (true, false) => self.base_stats_a.with_level(self.level_a),
+            (false, false) => self.base_stats_b.with_level(self.level_b),
+            (player_a, true) => {
+                let partner = self.partner(player_a).unwrap();
+                partner.base_stats.with_level(partner.level)
+            }
+        }
+    }
+
+    // Team sides pool the hit points of both figures
+    fn side_hit_points(&self, player_a: bool) -> u8 {
+        let partner = self
+            .partner(player_a)
+            .map_or(0, |_| self.stats(player_a, true).hit_points());
+        self.stats(player_a, false).hit_points() + partner
+    }
+
+    // Damage dealt and taken by one account
+    pub fn damage_by(&self, account_id: &AccountId) -> (u32, u32) {
+        self.turns.iter().fold((0, 0), |(dealt, taken), turn| {
+            if turn.attacker == *account_id {
+                (dealt + turn.damage as u32, taken)
+            } else if turn.defender == *account_id {
+                (dealt, taken + turn.damage as u32)
+            } else {
+                (dealt, taken)
+            }
+        })
+    }
+
+    pub fn total_damage(&self) -> (u32, u32) {
+        self.turns
+            .iter()
+            .enumerate()
+            .fold((0, 0), |(damage_a, damage_b), (i, turn)| {
+                if self.is_player_a_turn(i) {
+                    (damage_a + turn.damage as u32, damage_b)
+                } else {
+                    (damage_a, damage_b + turn.damage as u32)
+                }
+            })
+    }
+
+    pub fn reset_hit_points(&mut self) {
+        if let DuelMode::Knockout = self.mode {
+            self.hp_a = Some(self.side_hit_points(true));
+            self.hp_b = self.figure_b.as_ref().map(|_| self.side_hit_points(false));
+        }
+    }
+
+    pub fn round_outcome(&self) -> Option<Winner> {
+        if self.hp_b == Some(0) {
+            return Some(Winner::PlayerA);
+        }
+        if self.hp_a == Some(0) {
+            return Some(Winner::PlayerB);
+        }
+
+        let start = self.round_start(self.turns.len());
+        let round_turns = &self.turns[start..];
+        if round_turns.len() < self.turns_per_round as usize {
+            return None;
+        }
+
+        // Knockout rounds that hit the turn cap go to whoever has more hit points left
+        let (score_a, score_b) = match self.mode {
+            DuelMode::Damage => (
+                round_turns.iter().step_by(2).map(|t| t.damage as u32).sum(),
+                round_turns
+                    .iter()
+                    .skip(1)
+                    .step_by(2)
+                    .map(|t| t.damage as u32)
+                    .sum(),
+            ),
+            DuelMode::Knockout => (self.hp_a.unwrap_or(0) as u32, self.hp_b.unwrap_or(0) as u32),
+        };
+        Some(Self::compare(score_a, score_b))
+    }
+
+    pub fn match_outcome(&self) -> Option<Winner> {
+        let wins_a = self
+            .round_results
+            .iter()
+            .filter(|w| matches!(w, Winner::PlayerA))
+            .count() as u8;
+        let wins_b = self
+            .round_results
+            .iter()
+            .filter(|w| matches!(w, Winner::PlayerB))
+            .count() as u8;
+
+        let needed = self.rounds / 2 + 1;
+        if wins_a >= needed {
+            return Some(Winner::PlayerA);
+        }
+        if wins_b >= needed {
+            return Some(Winner::PlayerB);
+        }
+        if self.round_results.len() < self.rounds as usize {
+            return None;
+        }
+
+        // Drawn rounds can leave every round played without a majority
+        Some(Self::compare(wins_a as u32, wins_b as u32))
+    }
+
+    fn compare(score_a: u32, score_b: u32) -> Winner {
+        match score_a.cmp(&score_b) {
+            cmp::Ordering::Greater => Winner::PlayerA,
+            cmp::Ordering::Less => Winner::PlayerB,
+            cmp::Ordering::Equal => Winner::Draw,
+        }
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct QueueEntry {
+    pub account_id: AccountId,
+    pub figure: FigureId,
+    pub min_stake: U128,
+    pub max_stake: U128,
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub creation_time: u64,
+}
+
+impl QueueEntry {
+    // Highest stake both entries accept, if their ranges overlap
+    pub fn common_stake(&self, other: &QueueEntry) -> Option<u128> {
+        let low = cmp::max(self.min_stake.0, other.min_stake.0);
+        let high = cmp::min(self.max_stake.0, other.max_stake.0);
+        (low <= high).then_some(high)
+    }
+
+    pub fn accepts_rating(&self, rating: u32) -> bool {
+        self.min_rating.is_none_or(|min| rating >= min)
+            && self.max_rating.is_none_or(|max| rating <= max)
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Copy, Clone, PartialEq)]
+pub enum TournamentStatus {
+    Registration,
+    InProgress,
+    Finished,
+    Canceled,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Entrant {
+    pub account_id: AccountId,
+    pub figure: FigureId,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Tournament {
+    pub id: U128,
+    pub creation_time: u64,
+    pub entry_fee: U128,
+    pub max_players: u8,
+    // Percentage of the prize pool for each finishing rank: champion, runner-up,
+    // semi-final losers and so on. Players sharing a rank split its share.
+    pub prize_split: Vec<u8>,
+    pub options: DuelOptions,
+    pub entrants: Vec<Entrant>,
+    pub brackets: Vec<Vec<U128>>,
+    pub placings: Vec<AccountId>,
+    pub status: TournamentStatus,
+}
+
+impl Tournament {
+    pub fn prize_pool(&self) -> u128 {
+        self.entry_fee.0 * self.entrants.len() as u128
+    }
+
+    pub fn entrant(&self, account_id: &AccountId) -> Option<&Entrant> {
+        self.entrants.iter().find(|e| &e.account_id == account_id)
+    }
+}
+
+// A royale player. Stats are snapshotted as the royale begins.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Fighter {
+    pub account_id: AccountId,
+    pub figure: FigureId,
+    pub version: u32,
+    pub base_stats: Stats,
+    pub level: u8,
+    pub max_hp: u8,
+    pub hp: u8,
+    // sha256 of the secret the fighter mixes into every roll, revealed once the royale is full
+    pub commitment: Base64VecU8,
+    pub secret: Option<Base64VecU8>,
+    // Index of the turn that knocked the fighter out, or the turns taken when they timed out
+    pub eliminated_at: Option<u32>,
+}
+
+impl Fighter {
+    pub fn stats(&self) -> Stats {
+        self.base_stats.with_level(self.level)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.eliminated_at.is_none()
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct RoyaleTurn {
+    pub creation_time: u64,
+    pub attacker: AccountId,
+    pub target: AccountId,
+    pub figure: FigureId,
+    pub target_figure: FigureId,
+    pub base_stat: u8,
+    pub max_damage: u8,
+    pub roll: u8,
+    pub bonus: u8,
+    pub penalty: u8,
+    pub damage: u8,
+    pub style: RoastStyle,
+    pub critical: bool,
+    pub dodged: bool,
+    pub eliminated: bool,
+    pub seed: Base64VecU8,
+    pub roast_cid: Option<String>,
+}
+
+// A free-for-all that begins once `max_players` have joined. Every player stakes the same
+// amount and the last one standing takes the pot.
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Royale {
+    pub id: U128,
+    pub creation_time: u64,
+    pub start_time: Option<u64>,
+    pub stake: U128,
+    pub token: AccountId,
+    pub fee_percent: u8,
+    pub accept_timeout: u64,
+    pub turn_timeout: u64,
+    // When the fighters still owe their secrets, or the fighter to move owes a turn, run out
+    // of time. Set once the royale is full.
+    pub deadline: Option<u64>,
+    pub max_players: u8,
+    // Fighters are out once their hit points fall to this percentage of where they started
+    pub knockout_percent: u8,
+    pub fighters: Vec<Fighter>,
+    pub turns: Vec<RoyaleTurn>,
+    pub winner: Option<AccountId>,
+}
+
+impl Royale {
+    pub fn new(
+        id: u128,
+        stake: U128,
+        token: AccountId,
+        config: &Config,
+        max_players: u8,
+        knockout_percent: u8,
+    ) -> Self {
+        Self {
+            id: U128(id),
+            creation_time: env::block_timestamp(),
+            start_time: None,
+            stake,
+            token,
+            fee_percent: config.fee_percent,
+            accept_timeout: config.accept_timeout,
+            turn_timeout: config.turn_timeout,
+            deadline: None,
+            max_players,
+            knockout_percent,
+            fighters: Vec::new(),
+            turns: Vec::new(),
+            winner: None,
+        }
+    }
+
+    pub fn pot(&self) -> u128 {
+        self.stake.0 * self.fighters.len() as u128
+    }
+
+    pub fn fighter(&self, account_id: &AccountId) -> Option<usize> {
+        self.fighters
+            .iter()
+            .position(|fighter| &fighter.account_id == account_id)
+    }
+
+    pub fn survivors(&self) -> impl Iterator<Item = &Fighter> {
+        self.fighters.iter().filter(|fighter| fighter.is_alive())
+    }
+
+    // Turns rotate through the fighters still standing, in the order they joined. A fighter
+    // eliminated for timing out is simply skipped.
+    pub fn current(&self) -> usize {
+        let next = self
+            .turns
+            .last()
+            .map_or(0, |turn| self.fighter(&turn.attacker).unwrap() + 1);
+        (0..self.fighters.len())
+            .map(|offset| (next + offset) % self.fighters.len())
+            .find(|&i| self.fighters[i].is_alive())
+            .unwrap()
+    }
+
+    // Turns begin once every fighter still standing has revealed their secret
+    pub fn is_revealed(&self) -> bool {
+        self.survivors().all(|fighter| fighter.secret.is_some())
+    }
+
+    pub fn extend_deadline(&mut self) {
+        self.deadline = Some(env::block_timestamp() + self.turn_timeout);
+    }
+
+    pub fn is_knocked_out(&self, fighter: &Fighter) -> bool {
+        fighter.hp as u32 * 100 <= fighter.max_hp as u32 * self.knockout_percent as u32
+    }
+
+    // Mixes every revealed secret with the block seed, like duel rolls, so no single fighter
+    // or validator can predict the roll. Fighters who timed out before revealing add nothing.
+    // Each byte of the hash is an independent roll.
+    pub fn roll(&self, turn: usize, seed: &[u8]) -> Vec<u8> {
+        let mut input = Vec::with_capacity(32 * self.fighters.len() + seed.len() + 24);
+        for secret in self
+            .fighters
+            .iter()
+            .filter_map(|fighter| fighter.secret.as_ref())
+        {
+            input.extend_from_slice(&secret.0);
+        }
+        input.extend_from_slice(seed);
+        input.extend_from_slice(&self.id.0.to_le_bytes());
+        input.extend_from_slice(&(turn as u64).to_le_bytes());
+
+        env::sha256(&input)
+    }
+
+    // Styles counter the last roast the target threw, whoever it was aimed at
+    pub fn damage(&self, attacker: usize, target: usize, style: RoastStyle, roll: &[u8]) -> Hit {
+        let target = &self.fighters[target];
+        let last_roast = self
+            .turns
+            .iter()
+            .rev()
+            .find(|turn| turn.attacker == target.account_id);
+        let strong = last_roast.is_some_and(|t| style.is_strong_against(t.style));
+        let weak = last_roast.is_some_and(|t| style.is_weak_against(t.style));
+
+        let stats = self.fighters[attacker].stats();
+        let stat_damage = style.stat(&stats);
+        let max_damage = if strong { stat_damage + 5 } else { stat_damage };
+
+        let dice = (roll[0] % max_damage) + 1;
+        let mut damage = dice;
+        let (mut bonus, mut penalty) = (0, 0);
+        if weak {
+            let weakened = cmp::max(damage.saturating_sub(5), 1);
+            penalty += damage - weakened;
+            damage = weakened;
+        }
+
+        let dodged = roll[3] % 100 < target.stats().strategy * DODGE_CHANCE_PER_STAT;
+        let critical = !dodged && roll[2] % 100 < stats.highest() * CRITICAL_CHANCE_PER_STAT;
+        if dodged {
+            penalty += damage;
+            damage = 0;
+        } else if critical {
+            let doubled = damage.saturating_mul(2);
+            bonus += doubled - damage;
+            damage = doubled;
+        }
+
+        Hit {
+            base_stat: stat_damage,
+            max_damage,
+            roll: dice,
+            bonus,
+            penalty,
+            damage,
+            critical,
+            dodged,
+        }
+    }
+
+    // Damage dealt and taken by a fighter over the whole royale
+    pub fn damage_by(&self, account_id: &AccountId) -> (u32, u32) {
+        self.turns.iter().fold((0, 0), |(dealt, taken), turn| {
+            let damage = turn.damage as u32;
+            (
+                dealt
+                    + if &turn.attacker == account_id {
+                        damage
+                    } else {
+                        0
+                    },
+                taken
+                    + if &turn.target == account_id {
+                        damage
+                    } else {
+                        0
+                    },
+            )
+        })
+    }
 }
@@ -0,0 +1,1794 @@
+use super::*;
+use near_sdk::mock::MockAction;
+use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+use near_sdk::testing_env;
+
+const ONE: u128 = 10u128.pow(24);
+
+fn roasti() -> AccountId {
+    "roasti.near".parse().unwrap()
+}
+
+fn usdc() -> AccountId {
+    "usdc.near".parse().unwrap()
+}
+
+fn set_caller(account_id: &AccountId) {
+    set_caller_at(account_id, 0);
+}
+
+fn set_caller_at(account_id: &AccountId, timestamp: u64) {
+    testing_env!(VMContextBuilder::new()
+        .predecessor_account_id(account_id.clone())
+        .attached_deposit(NearToken::from_yoctonear(1))
+        .block_timestamp(timestamp)
+        .build());
+}
+
+fn setup() -> DuelManagerContract {
+    set_caller(&accounts(0));
+    DuelManagerContract::new(accounts(0), roasti())
+}
+
+fn deposit(contract: &mut DuelManagerContract, account_id: &AccountId, amount: u128) {
+    deposit_in(contract, &roasti(), account_id, amount);
+}
+
+fn deposit_in(
+    contract: &mut DuelManagerContract,
+    token: &AccountId,
+    account_id: &AccountId,
+    amount: u128,
+) {
+    set_caller(token);
+    contract.ft_on_transfer(account_id.clone(), U128(amount), String::new());
+}
+
+fn add_usdc(contract: &mut DuelManagerContract) {
+    set_caller(&accounts(0));
+    contract.add_token(usdc(), 6);
+}
+
+fn total_stake(contract: &DuelManagerContract, token: &AccountId) -> u128 {
+    contract.total_stakes[token]
+}
+
+fn balance(contract: &DuelManagerContract, account_id: &AccountId) -> u128 {
+    contract.get_balance(account_id.clone(), None).0
+}
+
+fn transfer_call(
+    contract: &mut DuelManagerContract,
+    token: &AccountId,
+    account_id: &AccountId,
+    amount: u128,
+    msg: Msg,
+) {
+    set_caller(token);
+    let msg = serde_json::to_string(&msg).unwrap();
+    contract.ft_on_transfer(account_id.clone(), U128(amount), msg);
+}
+
+// Account 1 creates a duel with Julius Caesar and account 2 accepts it with Sun Tzu, both
+// paying their stake in with the transfer. Neither secret is revealed yet.
+fn accepted_duel(
+    contract: &mut DuelManagerContract,
+    token: &AccountId,
+    stake: u128,
+    options: DuelOptions,
+) -> U128 {
+    let duel_id = U128(contract.next_duel_id);
+    let create = Msg::CreateDuel {
+        figure: Some("JuliusCaesar".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(1),
+        options,
+        opponent: None,
+    };
+    transfer_call(contract, token, &accounts(1), stake, create);
+    let accept = Msg::AcceptDuel {
+        duel_id,
+        figure: Some("SunTzu".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(2),
+    };
+    transfer_call(contract, token, &accounts(2), stake, accept);
+    duel_id
+}
+
+fn reveal_secrets(contract: &mut DuelManagerContract, duel_id: U128) {
+    for i in [1, 2] {
+        set_caller(&accounts(i));
+        contract.reveal_secret(duel_id, secret(i));
+    }
+}
+
+// Takes the next turn as whoever is to move
+fn play_turn(contract: &mut DuelManagerContract, duel_id: U128, style: RoastStyle) {
+    let duel = &contract.duels[&duel_id.0];
+    let turn = duel.turns.len();
+    let player = duel
+        .member(duel.is_player_a_turn(turn), duel.is_partner_turn(turn))
+        .0
+        .clone();
+    set_caller(&player);
+    contract.take_turn(duel_id, style);
+}
+
+fn play_out(contract: &mut DuelManagerContract, duel_id: U128) {
+    while contract.duels[&duel_id.0].winner.is_none() {
+        play_turn(contract, duel_id, RoastStyle::Witty);
+    }
+}
+
+#[test]
+fn played_rolls_can_be_verified() {
+    let mut contract = setup();
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    reveal_secrets(&mut contract, duel_id);
+    play_out(&mut contract, duel_id);
+
+    let turns = contract.duels[&duel_id.0].turns.len();
+    assert_eq!(contract.verify_rolls(duel_id), vec![true; turns]);
+
+    // A roll that doesn't follow from the secrets and seed is caught
+    contract.duels.get_mut(&duel_id.0).unwrap().turns[1].roll += 1;
+    let verified = contract.verify_rolls(duel_id);
+    assert!(!verified[1]);
+    assert_eq!(verified.iter().filter(|ok| **ok).count(), turns - 1);
+}
+
+#[test]
+#[should_panic(expected = "Secret does not match commitment.")]
+fn secrets_must_match_their_commitments() {
+    let mut contract = setup();
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    set_caller(&accounts(1));
+    contract.reveal_secret(duel_id, secret(2));
+}
+
+#[test]
+#[should_panic(expected = "Both secrets must be revealed before taking turns.")]
+fn turns_wait_for_both_secrets() {
+    let mut contract = setup();
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    set_caller(&accounts(1));
+    contract.reveal_secret(duel_id, secret(1));
+    contract.take_turn(duel_id, RoastStyle::Witty);
+}
+
+#[test]
+fn matched_duels_take_commitments() {
+    let mut contract = setup();
+    // Matchmaking starts the duel before either player has committed
+    for (i, figure) in [(1, "JuliusCaesar"), (2, "SunTzu")] {
+        let join = Msg::JoinQueue {
+            figure: figure.to_string(),
+            min_stake: U128(ONE),
+            max_stake: U128(ONE),
+            min_rating: None,
+            max_rating: None,
+        };
+        transfer_call(&mut contract, &roasti(), &accounts(i), ONE, join);
+    }
+    let duel_id = U128(0);
+
+    set_caller(&accounts(1));
+    contract.commit_secret(duel_id, secret_hash(1));
+    set_caller(&accounts(2));
+    contract.commit_secret(duel_id, secret_hash(2));
+    reveal_secrets(&mut contract, duel_id);
+    play_turn(&mut contract, duel_id, RoastStyle::Brutal);
+    assert_eq!(contract.duels[&duel_id.0].turns.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Secret already committed.")]
+fn commitments_cannot_be_replaced() {
+    let mut contract = setup();
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    set_caller(&accounts(1));
+    contract.commit_secret(duel_id, secret_hash(3));
+}
+
+fn active_duel(contract: &mut DuelManagerContract, stake: u128) -> U128 {
+    active_duel_in(contract, &roasti(), stake, false)
+}
+
+// An active duel between accounts 1 and 2 with both secrets revealed, each staking `stake`
+// through a transfer. Team duels add accounts 3 and 4 as partners.
+fn active_duel_in(
+    contract: &mut DuelManagerContract,
+    token: &AccountId,
+    stake: u128,
+    team: bool,
+) -> U128 {
+    let options = DuelOptions {
+        team,
+        turns: team.then_some(8),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(contract, token, stake, options);
+    if team {
+        for (i, side, figure) in [
+            (3, Side::PlayerA, "Socrates"),
+            (4, Side::PlayerB, "MarkTwain"),
+        ] {
+            deposit_in(contract, token, &accounts(i), stake);
+            set_caller(&accounts(i));
+            contract.join_team(duel_id, side, figure.to_string());
+        }
+    }
+    reveal_secrets(contract, duel_id);
+    duel_id
+}
+
+fn bet(
+    contract: &mut DuelManagerContract,
+    account_id: &AccountId,
+    duel_id: U128,
+    side: Side,
+    amount: u128,
+) {
+    deposit(contract, account_id, amount);
+    set_caller(account_id);
+    contract.place_bet(duel_id, side, U128(amount));
+}
+
+fn surrender(contract: &mut DuelManagerContract, i: usize, duel_id: U128) {
+    set_caller(&accounts(i));
+    contract.surrender(duel_id);
+}
+
+fn claim(contract: &mut DuelManagerContract, account_id: &AccountId, duel_id: U128) -> u128 {
+    set_caller(account_id);
+    contract.claim_bet(duel_id).0
+}
+
+#[test]
+fn winning_bets_split_the_pool_pro_rata() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(4), duel_id, Side::PlayerA, 2 * ONE);
+    bet(&mut contract, &accounts(5), duel_id, Side::PlayerB, ONE + 1);
+
+    surrender(&mut contract, 2, duel_id);
+    // The duel's 10% fee is burned from the pool at settlement
+    assert_eq!(contract.total_stakes[&roasti()], 36 * ONE / 10 + 1);
+
+    assert_eq!(claim(&mut contract, &accounts(5), duel_id), 0);
+    assert_eq!(claim(&mut contract, &accounts(3), duel_id), 12 * ONE / 10);
+    assert_eq!(claim(&mut contract, &accounts(4), duel_id), 24 * ONE / 10);
+    assert_eq!(balance(&contract, &accounts(3)), 12 * ONE / 10);
+    assert_eq!(balance(&contract, &accounts(4)), 24 * ONE / 10);
+    assert!(contract.bets.get(&duel_id.0).is_none());
+    assert!(contract.get_account_bets(accounts(3), None, 10).is_empty());
+}
+
+#[test]
+fn last_winning_claim_burns_the_dust() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(4), duel_id, Side::PlayerA, 2 * ONE);
+    bet(&mut contract, &accounts(5), duel_id, Side::PlayerB, ONE + 1);
+    surrender(&mut contract, 2, duel_id);
+
+    let payable = contract.bets[&duel_id.0].payable.0;
+    let paid =
+        claim(&mut contract, &accounts(3), duel_id) + claim(&mut contract, &accounts(4), duel_id);
+    assert_eq!(payable - paid, 1);
+    // Only the winnings are still held
+    let balances: u128 = (1..6).map(|i| balance(&contract, &accounts(i))).sum();
+    assert_eq!(contract.total_stakes[&roasti()], balances);
+    assert_eq!(balances, paid);
+}
+
+#[test]
+fn bets_are_refunded_when_the_duel_is_canceled() {
+    let mut contract = setup();
+    // Neither player reveals their secret
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(4), duel_id, Side::PlayerB, 3 * ONE);
+    let deadline = contract.duels[&duel_id.0].deadline.unwrap();
+    set_caller_at(&accounts(1), deadline);
+    contract.cancel_duel(duel_id);
+
+    assert_eq!(claim(&mut contract, &accounts(3), duel_id), ONE);
+    assert_eq!(claim(&mut contract, &accounts(4), duel_id), 3 * ONE);
+    assert_eq!(contract.total_stakes[&roasti()], 4 * ONE);
+}
+
+#[test]
+fn one_sided_pools_are_refunded() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(4), duel_id, Side::PlayerA, ONE);
+    surrender(&mut contract, 2, duel_id);
+
+    assert_eq!(claim(&mut contract, &accounts(3), duel_id), ONE);
+    assert_eq!(claim(&mut contract, &accounts(4), duel_id), ONE);
+}
+
+#[test]
+fn adding_to_a_bet_keeps_one_record() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+
+    let pool = &contract.bets[&duel_id.0];
+    assert_eq!((pool.bettors_a, pool.pool_a.0), (1, 2 * ONE));
+    assert_eq!(
+        contract.get_account_bets(accounts(3), None, 10)[0].amount.0,
+        2 * ONE
+    );
+}
+
+#[test]
+#[should_panic(expected = "You already bet on the other side.")]
+fn bets_cannot_hedge() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerB, ONE);
+}
+
+#[test]
+#[should_panic(expected = "Bet is below the minimum.")]
+fn bets_have_a_minimum() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(
+        &mut contract,
+        &accounts(3),
+        duel_id,
+        Side::PlayerA,
+        ONE / 100,
+    );
+}
+
+#[test]
+#[should_panic(expected = "This duel has reached the maximum number of bets.")]
+fn bets_per_duel_are_capped() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    for i in 0..=100 {
+        let bettor: AccountId = format!("bettor{}.near", i).parse().unwrap();
+        bet(&mut contract, &bettor, duel_id, Side::PlayerA, ONE);
+    }
+}
+
+#[test]
+#[should_panic(expected = "The duel is not settled yet.")]
+fn bets_are_claimed_after_settlement() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    claim(&mut contract, &accounts(3), duel_id);
+}
+
+#[test]
+#[should_panic(expected = "Bets close once the first turn is taken.")]
+fn bets_close_at_the_first_turn() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    bet(&mut contract, &accounts(3), duel_id, Side::PlayerA, ONE);
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    bet(&mut contract, &accounts(4), duel_id, Side::PlayerB, ONE);
+}
+
+fn secret(i: usize) -> Base64VecU8 {
+    Base64VecU8(vec![i as u8; 32])
+}
+
+fn secret_hash(i: usize) -> Base64VecU8 {
+    Base64VecU8(env::sha256(&secret(i).0))
+}
+
+// A full royale between accounts 1 to 3, each staking `stake`
+fn full_royale(contract: &mut DuelManagerContract, stake: u128) -> U128 {
+    let figures = ["JuliusCaesar", "SunTzu", "Socrates"];
+    for (i, figure) in (1..4).zip(figures) {
+        deposit(contract, &accounts(i), stake);
+        set_caller(&accounts(i));
+        if i == 1 {
+            contract.create_royale(
+                figure.to_string(),
+                U128(stake),
+                secret_hash(i),
+                3,
+                None,
+                None,
+            );
+        } else {
+            contract.join_royale(U128(0), figure.to_string(), secret_hash(i));
+        }
+    }
+    U128(0)
+}
+
+#[test]
+fn idle_royale_fighters_time_out() {
+    let mut contract = setup();
+    let royale_id = full_royale(&mut contract, ONE);
+    // Account 3 never reveals
+    for i in [1, 2] {
+        set_caller(&accounts(i));
+        contract.reveal_royale_secret(royale_id, secret(i));
+    }
+
+    set_caller_at(&accounts(1), contract.royales[&0].deadline.unwrap());
+    contract.claim_royale_timeout(royale_id);
+    let royale = &contract.royales[&0];
+    assert_eq!(royale.fighters[2].eliminated_at, Some(0));
+    assert_eq!(royale.current(), 0);
+
+    // Account 1 is to move and idles
+    set_caller_at(&accounts(2), royale.deadline.unwrap());
+    contract.claim_royale_timeout(royale_id);
+    assert_eq!(contract.royales[&0].winner, Some(accounts(2)));
+}
+
+#[test]
+#[should_panic(expected = "All fighters must reveal their secrets first.")]
+fn royale_turns_wait_for_every_secret() {
+    let mut contract = setup();
+    let royale_id = full_royale(&mut contract, ONE);
+    set_caller(&accounts(1));
+    contract.reveal_royale_secret(royale_id, secret(1));
+    contract.take_royale_turn(royale_id, accounts(2), RoastStyle::Witty);
+}
+
+#[test]
+#[should_panic(expected = "The deadline has not passed yet.")]
+fn royale_timeouts_wait_for_the_deadline() {
+    let mut contract = setup();
+    let royale_id = full_royale(&mut contract, ONE);
+    set_caller(&accounts(1));
+    contract.claim_royale_timeout(royale_id);
+}
+
+#[test]
+fn roast_queue_tells_duels_and_royales_apart() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    let royale_id = full_royale(&mut contract, ONE);
+    for i in [1, 2, 3] {
+        set_caller(&accounts(i));
+        contract.reveal_royale_secret(royale_id, secret(i));
+    }
+    let royale = &contract.royales[&royale_id.0];
+    let attacker = royale.fighters[royale.current()].account_id.clone();
+    let target = royale.fighters[(royale.current() + 1) % 3]
+        .account_id
+        .clone();
+    set_caller(&attacker);
+    contract.take_royale_turn(royale_id, target, RoastStyle::Witty);
+
+    let queue = contract.get_roast_queue();
+    assert_eq!(queue.len(), 2);
+    assert_eq!(
+        (queue[0].duel_id, queue[0].royale_id),
+        (Some(duel_id), None)
+    );
+    assert_eq!(
+        (queue[1].duel_id, queue[1].royale_id),
+        (None, Some(royale_id))
+    );
+}
+
+#[test]
+fn royales_nobody_reveals_in_are_refunded() {
+    let mut contract = setup();
+    let royale_id = full_royale(&mut contract, ONE);
+    set_caller_at(&accounts(2), contract.royales[&0].deadline.unwrap());
+    contract.claim_royale_timeout(royale_id);
+
+    assert!(contract.get_royale(royale_id).is_none());
+    for i in [1, 2, 3] {
+        assert_eq!(balance(&contract, &accounts(i)), ONE);
+    }
+    assert_eq!(total_stake(&contract, &roasti()), 3 * ONE);
+}
+
+#[test]
+fn any_fighter_can_cancel_an_unfilled_royale() {
+    let mut contract = setup();
+    deposit(&mut contract, &accounts(1), ONE);
+    deposit(&mut contract, &accounts(2), ONE);
+    set_caller(&accounts(1));
+    contract.create_royale(
+        "SunTzu".to_string(),
+        U128(ONE),
+        secret_hash(1),
+        3,
+        None,
+        None,
+    );
+    set_caller(&accounts(2));
+    contract.join_royale(U128(0), "Socrates".to_string(), secret_hash(2));
+
+    let accept_timeout = contract.royales[&0].accept_timeout;
+    set_caller_at(&accounts(2), accept_timeout);
+    contract.cancel_royale(U128(0));
+    assert_eq!(balance(&contract, &accounts(1)), ONE);
+    assert_eq!(balance(&contract, &accounts(2)), ONE);
+}
+
+#[test]
+fn royales_are_paged_newest_first() {
+    let mut contract = setup();
+    for i in 1..5 {
+        deposit(&mut contract, &accounts(i), ONE);
+        set_caller(&accounts(i));
+        contract.create_royale(
+            "SunTzu".to_string(),
+            U128(ONE),
+            secret_hash(i),
+            3,
+            None,
+            None,
+        );
+    }
+    let accept_timeout = contract.royales[&2].accept_timeout;
+    set_caller_at(&accounts(3), accept_timeout);
+    contract.cancel_royale(U128(2));
+
+    let ids = |page: Vec<Royale>| page.iter().map(|royale| royale.id.0).collect_vec();
+    assert_eq!(ids(contract.get_royales(None, 2)), [3, 1]);
+    assert_eq!(ids(contract.get_royales(Some(U128(1)), 2)), [0]);
+}
+
+// A house duel for account 1, playing Sun Tzu against Socrates with secret `i`, against a
+// bankroll of `bankroll`
+fn house_duel(contract: &mut DuelManagerContract, bankroll: u128, stake: u128, i: usize) -> U128 {
+    transfer_call(
+        contract,
+        &roasti(),
+        &accounts(0),
+        bankroll,
+        Msg::FundBankroll,
+    );
+    set_caller(&accounts(0));
+    contract.set_house_limits(U128(bankroll), U128(bankroll));
+
+    let duel_id = U128(contract.next_duel_id);
+    let create = Msg::CreateHouseDuel {
+        figure: "SunTzu".to_string(),
+        secret_hash: secret_hash(i),
+        house_figure: Some("Socrates".to_string()),
+        options: DuelOptions::default(),
+    };
+    transfer_call(contract, &roasti(), &accounts(1), stake, create);
+    duel_id
+}
+
+// The house replies to every turn right away, so only the player's turns are taken
+fn play_house_duel(contract: &mut DuelManagerContract, duel_id: U128, i: usize) -> Duel {
+    set_caller(&accounts(1));
+    contract.reveal_secret(duel_id, secret(i));
+    while contract.duels[&duel_id.0].winner.is_none() {
+        contract.take_turn(duel_id, RoastStyle::Strategic);
+    }
+    contract.duels[&duel_id.0].clone()
+}
+
+#[test]
+fn house_duels_reserve_the_matched_stake() {
+    let mut contract = setup();
+    house_duel(&mut contract, 10 * ONE, 2 * ONE, 1);
+
+    let bankroll = contract.get_bankroll();
+    assert_eq!(bankroll.balance.0, 8 * ONE);
+    assert_eq!(bankroll.exposure.0, 2 * ONE);
+}
+
+#[test]
+fn house_losses_pay_the_player_from_the_bankroll() {
+    let mut contract = setup();
+    let duel_id = house_duel(&mut contract, 10 * ONE, 2 * ONE, 2);
+    let duel = play_house_duel(&mut contract, duel_id, 2);
+    assert!(matches!(duel.winner, Some(Winner::PlayerA)));
+
+    let bankroll = contract.get_bankroll();
+    assert_eq!(bankroll.balance.0, 8 * ONE);
+    assert_eq!(bankroll.exposure.0, 0);
+    assert_eq!((bankroll.won.0, bankroll.lost.0), (0, 2 * ONE));
+    let stats = contract.get_player_stats(accounts(1));
+    assert_eq!(stats.roasti_won.0, 2 * ONE - duel.fee());
+}
+
+#[test]
+fn house_wins_return_the_pot_minus_the_fee() {
+    let mut contract = setup();
+    let duel_id = house_duel(&mut contract, 10 * ONE, 2 * ONE, 1);
+    let fee = contract.duels[&duel_id.0].fee();
+    set_caller(&accounts(1));
+    contract.surrender(duel_id);
+
+    let bankroll = contract.get_bankroll();
+    assert_eq!(bankroll.balance.0, 12 * ONE - fee);
+    assert_eq!(bankroll.exposure.0, 0);
+    assert_eq!((bankroll.won.0, bankroll.lost.0), (2 * ONE - fee, 0));
+    assert_eq!(
+        contract.get_player_stats(accounts(1)).roasti_lost.0,
+        2 * ONE
+    );
+}
+
+#[test]
+fn house_draws_return_both_stakes() {
+    let mut contract = setup();
+    let duel_id = house_duel(&mut contract, 10 * ONE, 2 * ONE, 3);
+    let duel = play_house_duel(&mut contract, duel_id, 3);
+    assert!(matches!(duel.winner, Some(Winner::Draw)));
+
+    let bankroll = contract.get_bankroll();
+    assert_eq!(bankroll.balance.0, 10 * ONE);
+    assert_eq!(
+        (bankroll.exposure.0, bankroll.won.0, bankroll.lost.0),
+        (0, 0, 0)
+    );
+}
+
+#[test]
+#[should_panic(expected = "The house is at its exposure limit.")]
+fn house_exposure_is_capped() {
+    let mut contract = setup();
+    house_duel(&mut contract, 10 * ONE, 6 * ONE, 1);
+
+    let create = Msg::CreateHouseDuel {
+        figure: "SunTzu".to_string(),
+        secret_hash: secret_hash(2),
+        house_figure: Some("Socrates".to_string()),
+        options: DuelOptions::default(),
+    };
+    transfer_call(&mut contract, &roasti(), &accounts(2), 6 * ONE, create);
+}
+
+#[test]
+fn live_clocks_count_down_the_side_to_move() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let duel = contract.duels.get_mut(&duel_id.0).unwrap();
+    duel.time_control = Some(TimeControl {
+        base: 1000,
+        increment: 0,
+    });
+    (duel.clock_a, duel.clock_b, duel.clock_start) = (Some(1000), Some(1000), Some(0));
+
+    set_caller_at(&accounts(1), 300);
+    assert_eq!(contract.get_clocks(duel_id), Some((700, 1000)));
+    set_caller_at(&accounts(1), 5000);
+    assert_eq!(contract.get_clocks(duel_id), Some((0, 1000)));
+}
+
+#[test]
+fn surrendering_hands_the_pot_to_the_opponent() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    surrender(&mut contract, 2, duel_id);
+
+    let duel = &contract.duels[&duel_id.0];
+    assert!(matches!(duel.winner, Some(Winner::PlayerA)));
+    assert!(matches!(duel.win_reason, Some(WinReason::Surrender)));
+    let fee = duel.fee();
+    assert_eq!(
+        contract.get_player_stats(accounts(1)).roasti_won.0,
+        ONE - fee
+    );
+    assert_eq!(contract.get_player_stats(accounts(2)).losses, 1);
+}
+
+#[test]
+fn partners_surrender_for_their_whole_side() {
+    let mut contract = setup();
+    let duel_id = active_duel_in(&mut contract, &roasti(), ONE, true);
+    surrender(&mut contract, 3, duel_id);
+
+    let duel = &contract.duels[&duel_id.0];
+    assert!(matches!(duel.winner, Some(Winner::PlayerB)));
+    assert!(matches!(duel.win_reason, Some(WinReason::Surrender)));
+}
+
+#[test]
+#[should_panic(expected = "Sender must be player.")]
+fn outsiders_cannot_surrender() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    surrender(&mut contract, 3, duel_id);
+}
+
+#[test]
+fn missed_turns_lose_on_time() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let deadline = contract.duels[&duel_id.0].deadline.unwrap();
+    set_caller_at(&accounts(2), deadline);
+    contract.claim_timeout_win(duel_id);
+
+    let duel = &contract.duels[&duel_id.0];
+    assert!(matches!(duel.winner, Some(Winner::PlayerB)));
+    assert!(matches!(duel.win_reason, Some(WinReason::Timeout)));
+}
+
+#[test]
+#[should_panic(expected = "The opponent still has time to move.")]
+fn turn_timeouts_wait_for_the_deadline() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let deadline = contract.duels[&duel_id.0].deadline.unwrap();
+    set_caller_at(&accounts(2), deadline - 1);
+    contract.claim_timeout_win(duel_id);
+}
+
+#[test]
+#[should_panic(expected = "Opponent is not overdue.")]
+fn the_side_to_move_cannot_claim_a_timeout() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let deadline = contract.duels[&duel_id.0].deadline.unwrap();
+    set_caller_at(&accounts(1), deadline);
+    contract.claim_timeout_win(duel_id);
+}
+
+// A duel between accounts 1 and 2 on a one minute clock with a ten second increment, with
+// both clocks started at 0
+fn clocked_duel(contract: &mut DuelManagerContract) -> U128 {
+    let options = DuelOptions {
+        time_control: Some(TimeControl {
+            base: 60 * 1_000_000_000,
+            increment: 10 * 1_000_000_000,
+        }),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(contract, &roasti(), ONE, options);
+    reveal_secrets(contract, duel_id);
+    duel_id
+}
+
+#[test]
+fn flags_fall_before_the_turn_deadline() {
+    let mut contract = setup();
+    let duel_id = clocked_duel(&mut contract);
+    set_caller_at(&accounts(1), 30 * 1_000_000_000);
+    contract.take_turn(duel_id, RoastStyle::Witty);
+    // Player A's move took 30 seconds and earned the increment back
+    assert_eq!(
+        contract.get_clocks(duel_id),
+        Some((40 * 1_000_000_000, 60 * 1_000_000_000))
+    );
+
+    set_caller_at(&accounts(1), 91 * 1_000_000_000);
+    contract.claim_timeout_win(duel_id);
+    let duel = &contract.duels[&duel_id.0];
+    assert!(env::block_timestamp() < duel.deadline.unwrap());
+    assert!(matches!(duel.winner, Some(Winner::PlayerA)));
+    assert!(matches!(duel.win_reason, Some(WinReason::FlagFall)));
+}
+
+#[test]
+#[should_panic(expected = "Out of time.")]
+fn moves_after_the_flag_falls_are_rejected() {
+    let mut contract = setup();
+    let duel_id = clocked_duel(&mut contract);
+    set_caller_at(&accounts(1), 61 * 1_000_000_000);
+    contract.take_turn(duel_id, RoastStyle::Witty);
+}
+
+#[test]
+fn min_stakes_are_per_token() {
+    let mut contract = setup();
+    add_usdc(&mut contract);
+    contract.set_min_stake(U128(5 * 10u128.pow(6)), Some(usdc()));
+
+    let tokens = contract.get_tokens();
+    assert_eq!(tokens[0].min_stake.0, ONE);
+    assert_eq!(
+        (tokens[1].decimals, tokens[1].min_stake.0),
+        (6, 5 * 10u128.pow(6))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Minimum stake must be positive and at most 1000 tokens.")]
+fn min_stakes_are_bounded_in_the_tokens_units() {
+    let mut contract = setup();
+    add_usdc(&mut contract);
+    contract.set_min_stake(U128(1001 * 10u128.pow(6)), Some(usdc()));
+}
+
+#[test]
+fn duel_settlement_pays_out_the_whole_pot_in_its_token() {
+    let mut contract = setup();
+    add_usdc(&mut contract);
+    deposit(&mut contract, &accounts(5), ONE);
+    let duel_id = active_duel_in(&mut contract, &usdc(), 10u128.pow(6) + 3, false);
+    assert_eq!(total_stake(&contract, &usdc()), 2 * 10u128.pow(6) + 6);
+
+    let duel = contract.duels[&duel_id.0].clone();
+    contract.settle_duel(duel, Winner::PlayerB, WinReason::Decision);
+    // The winnings and the fee both left the contract
+    assert_eq!(total_stake(&contract, &usdc()), 0);
+    assert_eq!(total_stake(&contract, &roasti()), ONE);
+}
+
+#[test]
+fn fees_in_other_tokens_go_to_the_treasury() {
+    let mut contract = setup();
+    add_usdc(&mut contract);
+    contract.set_treasury(accounts(5));
+    let duel_id = active_duel_in(&mut contract, &usdc(), 10u128.pow(6), false);
+    surrender(&mut contract, 2, duel_id);
+
+    let calls: Vec<(String, serde_json::Value)> = get_created_receipts()
+        .into_iter()
+        .filter(|receipt| receipt.receiver_id == usdc())
+        .flat_map(|receipt| receipt.actions)
+        .filter_map(|action| match action {
+            MockAction::FunctionCallWeight {
+                method_name, args, ..
+            } => Some((
+                String::from_utf8(method_name).unwrap(),
+                serde_json::from_slice(&args).unwrap(),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert!(calls.iter().all(|(method, _)| method == "ft_transfer"));
+    let fee = contract.duels[&duel_id.0].fee();
+    assert!(calls.iter().any(|(_, args)| {
+        args["receiver_id"] == accounts(5).as_str() && args["amount"] == fee.to_string()
+    }));
+}
+
+#[test]
+fn team_winners_split_the_pot_and_the_dust_is_burned() {
+    let mut contract = setup();
+    let duel_id = active_duel_in(&mut contract, &roasti(), ONE + 3, true);
+    let duel = contract.duels[&duel_id.0].clone();
+
+    // A pot of 4 ONE + 12 pays a fee of 0.4 ONE + 1 and leaves 1 behind when halved
+    assert_eq!(duel.fee(), 4 * ONE / 10 + 1);
+    assert_eq!(duel.payout_share(), 18 * ONE / 10 + 5);
+    contract.settle_duel(duel, Winner::PlayerA, WinReason::Decision);
+    assert_eq!(total_stake(&contract, &roasti()), 0);
+}
+
+#[test]
+fn royale_settlement_pays_out_the_whole_pot() {
+    let mut contract = setup();
+    let royale_id = full_royale(&mut contract, ONE);
+    for i in [1, 2, 3] {
+        set_caller(&accounts(i));
+        contract.reveal_royale_secret(royale_id, secret(i));
+    }
+    while contract.royales[&0].winner.is_none() {
+        let royale = &contract.royales[&0];
+        let claimant = royale
+            .survivors()
+            .map(|fighter| fighter.account_id.clone())
+            .find(|account_id| *account_id != royale.fighters[royale.current()].account_id)
+            .unwrap();
+        set_caller_at(&claimant, royale.deadline.unwrap());
+        contract.claim_royale_timeout(royale_id);
+    }
+    assert_eq!(total_stake(&contract, &roasti()), 0);
+}
+
+#[test]
+fn burn_excess_checks_removed_tokens() {
+    let mut contract = setup();
+    add_usdc(&mut contract);
+    deposit_in(&mut contract, &usdc(), &accounts(1), 10u128.pow(6));
+    set_caller(&accounts(0));
+    contract.remove_token(usdc());
+
+    contract.burn_excess();
+    let receivers: Vec<AccountId> = get_created_receipts()
+        .into_iter()
+        .map(|receipt| receipt.receiver_id)
+        .collect();
+    assert!(receivers.contains(&roasti()));
+    assert!(receivers.contains(&usdc()));
+}
+
+// The first contract layout, written the way that deployment stored it
+#[near(serializers = [borsh])]
+struct LegacyContract {
+    admin_id: AccountId,
+    ft_contract: AccountId,
+    duels: IterableMap<u128, LegacyDuel>,
+    next_duel_id: u128,
+    stakes: LookupMap<AccountId, u128>,
+    total_stake: u128,
+}
+
+#[near(serializers = [borsh])]
+struct LegacyDuel {
+    id: U128,
+    creation_time: u64,
+    start_time: Option<u64>,
+    stake: U128,
+    player_a: AccountId,
+    figure_a: u8,
+    player_b: Option<AccountId>,
+    figure_b: Option<u8>,
+    turns: Vec<(u64, u8, RoastStyle, Option<String>)>,
+    winner: Option<Winner>,
+}
+
+#[test]
+fn migration_converts_the_first_layout() {
+    set_caller(&accounts(0));
+    let mut legacy = LegacyContract {
+        admin_id: accounts(0),
+        ft_contract: roasti(),
+        duels: IterableMap::new(b"d"),
+        next_duel_id: 2,
+        stakes: LookupMap::new(b"s"),
+        total_stake: 2 * ONE,
+    };
+    // Caesar beat Sun Tzu, and a second duel was still waiting for an opponent
+    let turns = vec![
+        (1, 7, RoastStyle::Brutal, None),
+        (2, 3, RoastStyle::Witty, None),
+    ];
+    legacy.duels.insert(
+        0,
+        LegacyDuel {
+            id: U128(0),
+            creation_time: 0,
+            start_time: Some(1),
+            stake: U128(ONE),
+            player_a: accounts(1),
+            figure_a: 0,
+            player_b: Some(accounts(2)),
+            figure_b: Some(5),
+            turns,
+            winner: Some(Winner::PlayerA),
+        },
+    );
+    legacy.duels.insert(
+        1,
+        LegacyDuel {
+            id: U128(1),
+            creation_time: 3,
+            start_time: None,
+            stake: U128(ONE),
+            player_a: accounts(3),
+            figure_a: 2,
+            player_b: None,
+            figure_b: None,
+            turns: Vec::new(),
+            winner: None,
+        },
+    );
+    legacy.stakes.insert(accounts(1), ONE);
+    legacy.duels.flush();
+    legacy.stakes.flush();
+    env::state_write(&legacy);
+
+    let mut contract = DuelManagerContract::migrate();
+    assert_eq!(contract.migrate_duels(10), 0);
+
+    let duel = contract.get_duel(U128(0)).unwrap();
+    assert_eq!(duel.figure_a.as_deref(), Some("JuliusCaesar"));
+    assert_eq!(duel.figure_b.as_deref(), Some("SunTzu"));
+    assert_eq!(duel.turns[1].attacker, accounts(2));
+    // Caesar's brutality, then Sun Tzu's wit with the bonus for countering a brutal roast
+    assert_eq!(duel.turns[0].max_damage, 6);
+    assert_eq!(duel.turns[1].max_damage, 10);
+    assert!(contract.verify_rolls(U128(0)).is_empty());
+    assert!(contract.get_duel(U128(1)).is_none());
+    assert_eq!(contract.get_finished_duels(None, 10).len(), 1);
+    assert_eq!(contract.get_player_stats(accounts(1)).damage_dealt, 7);
+    assert_eq!(
+        contract.get_leaderboard_by_wins(None, 10)[0].account_id,
+        accounts(1)
+    );
+    assert!(contract.get_rating(accounts(1)) > DEFAULT_RATING);
+
+    assert_eq!(balance(&contract, &accounts(1)), ONE);
+    assert_eq!(balance(&contract, &accounts(3)), ONE);
+    assert_eq!(total_stake(&contract, &roasti()), 2 * ONE);
+    assert_eq!(contract.next_duel_id, 2);
+}
+
+#[test]
+fn knockouts_end_the_duel_before_the_turn_cap() {
+    let mut contract = setup();
+    let options = DuelOptions {
+        mode: DuelMode::Knockout,
+        turns: Some(20),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, options);
+    reveal_secrets(&mut contract, duel_id);
+    // Caesar and Sun Tzu both start with hit points equal to their stat total of 20
+    let duel = &contract.duels[&duel_id.0];
+    assert_eq!((duel.hp_a, duel.hp_b), (Some(20), Some(20)));
+
+    play_out(&mut contract, duel_id);
+    let duel = &contract.duels[&duel_id.0];
+    let (damage_a, damage_b) = duel.total_damage();
+    assert!(duel.turns.len() < 20);
+    assert_eq!(duel.hp_a, Some(20u32.saturating_sub(damage_b) as u8));
+    assert_eq!(duel.hp_b, Some(20u32.saturating_sub(damage_a) as u8));
+    match duel.winner.unwrap() {
+        Winner::PlayerA => assert!(duel.hp_b == Some(0) && duel.hp_a > Some(0)),
+        Winner::PlayerB => assert!(duel.hp_a == Some(0) && duel.hp_b > Some(0)),
+        Winner::Draw => panic!("Knockouts can't be drawn."),
+    }
+}
+
+#[test]
+fn best_of_three_settles_once_a_side_wins_two_rounds() {
+    let mut contract = setup();
+    let options = DuelOptions {
+        turns: Some(2),
+        rounds: Some(3),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, options);
+    reveal_secrets(&mut contract, duel_id);
+
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    let duel = &contract.duels[&duel_id.0];
+    assert_eq!(duel.round_results.len(), 1);
+    assert!(duel.winner.is_none());
+
+    play_out(&mut contract, duel_id);
+    let duel = &contract.duels[&duel_id.0];
+    let rounds = duel.round_results.len();
+    assert!(rounds == 2 || rounds == 3);
+    assert_eq!(duel.turns.len(), 2 * rounds);
+    assert_eq!(duel.turns[2].round, 1);
+    // Player A opens every round
+    assert_eq!(duel.turns[2].attacker, accounts(1));
+
+    let wins_a = duel
+        .round_results
+        .iter()
+        .filter(|w| matches!(w, Winner::PlayerA))
+        .count();
+    let wins_b = duel
+        .round_results
+        .iter()
+        .filter(|w| matches!(w, Winner::PlayerB))
+        .count();
+    match duel.winner.unwrap() {
+        Winner::PlayerA => assert!(wins_a > wins_b),
+        Winner::PlayerB => assert!(wins_b > wins_a),
+        Winner::Draw => assert_eq!(wins_a, wins_b),
+    }
+}
+
+#[test]
+#[should_panic(expected = "Turn count must be even and within bounds.")]
+fn turn_counts_must_be_even() {
+    let mut contract = setup();
+    let options = DuelOptions {
+        turns: Some(3),
+        ..Default::default()
+    };
+    accepted_duel(&mut contract, &roasti(), ONE, options);
+}
+
+#[test]
+fn stalled_bracket_duels_can_be_settled_by_anyone() {
+    let mut contract = setup();
+    contract.create_tournament(U128(ONE), 4, vec![100], None);
+    let figures = ["JuliusCaesar", "SunTzu", "Socrates", "MarkTwain"];
+    for (i, figure) in (1..5).zip(figures) {
+        let join = Msg::JoinTournament {
+            tournament_id: U128(0),
+            figure: figure.to_string(),
+        };
+        transfer_call(&mut contract, &roasti(), &accounts(i), ONE, join);
+    }
+    let deadline = contract.duels[&0].deadline.unwrap();
+
+    // Nobody shows up for the first semi-final, and only player A commits in the second
+    let committed = contract.duels[&1].player_a.clone();
+    set_caller(&committed);
+    contract.commit_secret(U128(1), secret_hash(1));
+    for duel_id in [0, 1] {
+        set_caller_at(&accounts(5), deadline);
+        contract.settle_bracket_timeout(U128(duel_id));
+    }
+    let (first, second) = (&contract.duels[&0], &contract.duels[&1]);
+    assert!(matches!(first.winner, Some(Winner::Draw)));
+    assert!(matches!(second.winner, Some(Winner::PlayerA)));
+
+    // The final is played out as usual and pays the champion
+    let tournament = contract.get_tournament(U128(0)).unwrap();
+    assert_eq!(tournament.brackets.len(), 2);
+    let final_id = tournament.brackets[1][0];
+    let final_duel = &contract.duels[&final_id.0];
+    let finalists = [first.player_a.clone(), committed];
+    assert_eq!(final_duel.player_a, finalists[0]);
+    assert_eq!(final_duel.player_b.as_ref(), Some(&finalists[1]));
+    for (i, player) in finalists.iter().enumerate() {
+        set_caller(player);
+        contract.commit_secret(final_id, secret_hash(i));
+    }
+    for (i, player) in finalists.iter().enumerate() {
+        set_caller(player);
+        contract.reveal_secret(final_id, secret(i));
+    }
+    play_out(&mut contract, final_id);
+
+    let tournament = contract.get_tournament(U128(0)).unwrap();
+    assert!(tournament.status == TournamentStatus::Finished);
+    assert_eq!(tournament.placings.len(), 4);
+    assert_eq!(total_stake(&contract, &roasti()), 0);
+}
+
+#[test]
+#[should_panic(expected = "The players still have time to move.")]
+fn bracket_timeouts_wait_for_the_deadline() {
+    let mut contract = setup();
+    contract.create_tournament(U128(ONE), 2, vec![100], None);
+    for (i, figure) in [(1, "JuliusCaesar"), (2, "SunTzu")] {
+        let join = Msg::JoinTournament {
+            tournament_id: U128(0),
+            figure: figure.to_string(),
+        };
+        transfer_call(&mut contract, &roasti(), &accounts(i), ONE, join);
+    }
+    set_caller(&accounts(5));
+    contract.settle_bracket_timeout(U128(0));
+}
+
+#[test]
+fn tournaments_are_paged_newest_first() {
+    let mut contract = setup();
+    for _ in 0..3 {
+        contract.create_tournament(U128(ONE), 2, vec![100], None);
+    }
+    let ids = |page: Vec<Tournament>| page.iter().map(|t| t.id.0).collect_vec();
+    assert_eq!(ids(contract.get_tournaments(None, 2)), [2, 1]);
+    assert_eq!(ids(contract.get_tournaments(Some(U128(1)), 2)), [0]);
+}
+
+// Account 1 challenges account 3 with Julius Caesar
+fn challenge(contract: &mut DuelManagerContract) -> U128 {
+    let duel_id = U128(contract.next_duel_id);
+    let create = Msg::CreateDuel {
+        figure: Some("JuliusCaesar".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(1),
+        options: DuelOptions::default(),
+        opponent: Some(accounts(3)),
+    };
+    transfer_call(contract, &roasti(), &accounts(1), ONE, create);
+    duel_id
+}
+
+#[test]
+fn challenges_can_only_be_accepted_by_the_opponent() {
+    let mut contract = setup();
+    let duel_id = challenge(&mut contract);
+    let incoming = contract.get_incoming_challenges(accounts(3), None, 10);
+    assert_eq!(incoming.len(), 1);
+    assert_eq!(incoming[0].id, duel_id);
+
+    let accept = Msg::AcceptDuel {
+        duel_id,
+        figure: Some("SunTzu".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(3),
+    };
+    transfer_call(&mut contract, &roasti(), &accounts(3), ONE, accept);
+    assert_eq!(contract.duels[&duel_id.0].player_b, Some(accounts(3)));
+    assert!(contract
+        .get_incoming_challenges(accounts(3), None, 10)
+        .is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Duel is reserved for another opponent.")]
+fn challenges_cannot_be_taken_by_others() {
+    let mut contract = setup();
+    let duel_id = challenge(&mut contract);
+    let accept = Msg::AcceptDuel {
+        duel_id,
+        figure: Some("SunTzu".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(2),
+    };
+    transfer_call(&mut contract, &roasti(), &accounts(2), ONE, accept);
+}
+
+#[test]
+fn declined_challenges_refund_the_challenger() {
+    let mut contract = setup();
+    let duel_id = challenge(&mut contract);
+    set_caller(&accounts(3));
+    contract.decline_duel(duel_id);
+
+    assert!(contract.get_duel(duel_id).is_none());
+    assert!(contract
+        .get_incoming_challenges(accounts(3), None, 10)
+        .is_empty());
+    assert_eq!(total_stake(&contract, &roasti()), 0);
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, roasti());
+}
+
+#[test]
+#[should_panic(expected = "Only the challenged account can decline.")]
+fn only_the_opponent_can_decline() {
+    let mut contract = setup();
+    let duel_id = challenge(&mut contract);
+    set_caller(&accounts(2));
+    contract.decline_duel(duel_id);
+}
+
+// Queues an account with the top of its stake range paid in
+fn queue(
+    contract: &mut DuelManagerContract,
+    i: usize,
+    figure: &str,
+    min_stake: u128,
+    max_stake: u128,
+) {
+    let join = Msg::JoinQueue {
+        figure: figure.to_string(),
+        min_stake: U128(min_stake),
+        max_stake: U128(max_stake),
+        min_rating: None,
+        max_rating: None,
+    };
+    transfer_call(contract, &roasti(), &accounts(i), max_stake, join);
+}
+
+#[test]
+fn queued_players_are_paired_at_the_highest_common_stake() {
+    let mut contract = setup();
+    queue(&mut contract, 1, "JuliusCaesar", ONE, 3 * ONE);
+    assert_eq!(contract.get_queue().len(), 1);
+    queue(&mut contract, 2, "SunTzu", 2 * ONE, 5 * ONE);
+
+    assert!(contract.get_queue().is_empty());
+    let duel = &contract.duels[&0];
+    assert_eq!(duel.stake.0, 3 * ONE);
+    assert_eq!(duel.player_a, accounts(1));
+    assert_eq!(duel.player_b, Some(accounts(2)));
+    assert!(duel.start_time.is_some());
+    assert_eq!(balance(&contract, &accounts(1)), 0);
+    assert_eq!(balance(&contract, &accounts(2)), 2 * ONE);
+}
+
+#[test]
+fn incompatible_entries_stay_queued() {
+    let mut contract = setup();
+    queue(&mut contract, 1, "JuliusCaesar", ONE, ONE);
+    // Stake ranges that don't overlap, then the same figure
+    queue(&mut contract, 2, "SunTzu", 2 * ONE, 2 * ONE);
+    queue(&mut contract, 3, "JuliusCaesar", ONE, ONE);
+    assert_eq!(contract.get_queue().len(), 3);
+    assert!(contract.duels.is_empty());
+
+    set_caller(&accounts(1));
+    contract.leave_queue();
+    assert_eq!(contract.get_queue().len(), 2);
+    assert_eq!(balance(&contract, &accounts(1)), ONE);
+}
+
+// Account 1 wins a duel against account 2 by surrender
+fn surrendered_duel(contract: &mut DuelManagerContract, loser: usize) {
+    let duel_id = accepted_duel(contract, &roasti(), ONE, DuelOptions::default());
+    set_caller(&accounts(loser));
+    contract.surrender(duel_id);
+}
+
+#[test]
+fn ratings_move_by_how_surprising_the_result_is() {
+    let mut contract = setup();
+    surrendered_duel(&mut contract, 2);
+    assert_eq!(contract.get_rating(accounts(1)), 1216);
+    assert_eq!(contract.get_rating(accounts(2)), 1184);
+    let leaders = contract.get_leaderboard_by_rating(None, 2);
+    assert_eq!(leaders[0].account_id, accounts(1));
+    assert_eq!(leaders[1].account_id, accounts(2));
+
+    // The underdog gains more for an upset than the favourite did for the expected win
+    surrendered_duel(&mut contract, 1);
+    let (rating_a, rating_b) = (
+        contract.get_rating(accounts(1)),
+        contract.get_rating(accounts(2)),
+    );
+    assert_eq!(rating_a + rating_b, 2400);
+    assert!(rating_b - 1184 > 16);
+    let leaders = contract.get_leaderboard_by_rating(None, 2);
+    assert_eq!(leaders[0].account_id, accounts(2));
+}
+
+#[test]
+fn matchmaking_respects_rating_ranges() {
+    let mut contract = setup();
+    surrendered_duel(&mut contract, 2);
+
+    let join = Msg::JoinQueue {
+        figure: "Socrates".to_string(),
+        min_stake: U128(ONE),
+        max_stake: U128(ONE),
+        min_rating: Some(1200),
+        max_rating: None,
+    };
+    transfer_call(&mut contract, &roasti(), &accounts(1), ONE, join);
+    queue(&mut contract, 2, "MarkTwain", ONE, ONE);
+    assert_eq!(contract.get_queue().len(), 2);
+    queue(&mut contract, 3, "SunTzu", ONE, ONE);
+    assert_eq!(contract.get_queue().len(), 1);
+    let duel = &contract.duels[&(contract.next_duel_id - 1)];
+    assert_eq!(duel.player_a, accounts(1));
+    assert_eq!(duel.player_b, Some(accounts(3)));
+}
+
+#[test]
+fn duels_move_through_the_status_listings() {
+    let mut contract = setup();
+    let create = Msg::CreateDuel {
+        figure: Some("Socrates".to_string()),
+        figure_hash: None,
+        secret_hash: secret_hash(1),
+        options: DuelOptions::default(),
+        opponent: None,
+    };
+    transfer_call(&mut contract, &roasti(), &accounts(1), ONE, create);
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+    let ids = |page: Vec<Duel>| page.iter().map(|duel| duel.id.0).collect_vec();
+    assert_eq!(ids(contract.get_pending_duels(None, 10)), [0]);
+    assert_eq!(ids(contract.get_active_duels(None, 10)), [1]);
+    assert_eq!(
+        ids(contract.get_account_duels(accounts(1), None, 10)),
+        [1, 0]
+    );
+    assert_eq!(
+        ids(contract.get_account_duels(accounts(1), Some(U128(1)), 10)),
+        [0]
+    );
+    assert_eq!(ids(contract.get_account_duels(accounts(2), None, 10)), [1]);
+
+    reveal_secrets(&mut contract, duel_id);
+    play_out(&mut contract, duel_id);
+    assert!(contract.get_active_duels(None, 10).is_empty());
+    assert_eq!(ids(contract.get_finished_duels(None, 10)), [1]);
+
+    let accept_timeout = contract.duels[&0].accept_timeout;
+    set_caller_at(&accounts(1), accept_timeout);
+    contract.cancel_duel(U128(0));
+    assert!(contract.get_pending_duels(None, 10).is_empty());
+    assert_eq!(ids(contract.get_account_duels(accounts(1), None, 10)), [1]);
+}
+
+#[test]
+fn finished_duels_level_up_the_figures_played() {
+    let mut contract = setup();
+    // Caesar wins every duel for 30 XP and Sun Tzu loses them for 10
+    for _ in 0..14 {
+        surrendered_duel(&mut contract, 2);
+    }
+    let caesar = contract.get_figure_progress(accounts(1), "JuliusCaesar".to_string());
+    assert_eq!(
+        (caesar.xp, caesar.level, caesar.next_level_xp),
+        (420, 4, Some(500))
+    );
+    assert_eq!(caesar.stats.wit, 6);
+    let sun_tzu = contract.get_figure_progress(accounts(2), "SunTzu".to_string());
+    assert_eq!((sun_tzu.xp, sun_tzu.level), (140, 1));
+    assert_eq!(sun_tzu.stats.wit, 5);
+
+    let figures = contract.get_figures(Some(accounts(1)));
+    let listed = figures.iter().find(|f| f.id == "JuliusCaesar").unwrap();
+    assert_eq!((listed.level, listed.wit), (4, 6));
+
+    // The bonus counts in the next duel
+    let duel_id = active_duel(&mut contract, ONE);
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    assert_eq!(contract.duels[&duel_id.0].turns[0].base_stat, 6);
+}
+
+#[test]
+fn every_launch_figure_has_its_own_signature_move() {
+    let contract = setup();
+    let figures = contract.get_figures(None);
+    assert_eq!(figures.len(), 30);
+    let moves = figures.iter().map(|f| f.signature_move as u8).unique();
+    assert_eq!(moves.count(), 30);
+}
+
+#[test]
+fn signature_moves_change_the_roast() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let duel = &contract.duels[&duel_id.0];
+    let roast = |signature, roll: &[u8]| duel.damage(0, RoastStyle::Witty, signature, roll);
+
+    // The lowest dice, with no critical hit or dodge
+    let mut roll = [0; 32];
+    roll[2] = 99;
+    roll[3] = 99;
+    assert_eq!(roast(None, &roll).damage, 1);
+    let masterpiece = roast(Some(SignatureMove::Masterpiece), &roll);
+    assert_eq!((masterpiece.roll, masterpiece.damage), (5, 5));
+    let epigram = roast(Some(SignatureMove::Epigram), &roll);
+    assert_eq!((epigram.bonus, epigram.damage), (3, 4));
+    let impale = roast(Some(SignatureMove::Impale), &roll);
+    assert!(impale.critical);
+    assert_eq!(impale.damage, 2);
+
+    // A sure dodge, unless the move can't be dodged
+    roll[3] = 0;
+    assert!(roast(None, &roll).dodged);
+    let battery = roast(Some(SignatureMove::GrandBattery), &roll);
+    assert!(!battery.dodged);
+    assert_eq!(battery.max_damage, 10);
+}
+
+#[test]
+#[should_panic(expected = "Signature move already used.")]
+fn signature_moves_are_used_once_per_duel() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    set_caller(&accounts(1));
+    contract.take_signature_turn(duel_id, RoastStyle::Witty);
+    let turn = &contract.duels[&duel_id.0].turns[0];
+    assert!(turn.signature == Some(SignatureMove::VeniVidiVici));
+    assert_eq!(contract.verify_rolls(duel_id), [true]);
+
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+    set_caller(&accounts(1));
+    contract.take_signature_turn(duel_id, RoastStyle::Witty);
+}
+
+#[test]
+fn critical_and_dodge_chances_follow_the_stats() {
+    let mut contract = setup();
+    let duel_id = active_duel(&mut contract, ONE);
+    let duel = &contract.duels[&duel_id.0];
+    let roast = |dice: u8, critical: u8, dodge: u8| {
+        let mut roll = [0; 32];
+        (roll[0], roll[2], roll[3]) = (dice, critical, dodge);
+        duel.damage(0, RoastStyle::Witty, None, &roll)
+    };
+
+    // Caesar's highest stat of 6 lands a critical hit 12% of the time, and Sun Tzu's strategy
+    // of 10 dodges 10% of the time
+    let critical = roast(2, 11, 10);
+    assert!(critical.critical && !critical.dodged);
+    assert_eq!((critical.roll, critical.bonus, critical.damage), (3, 3, 6));
+    assert!(!roast(2, 12, 10).critical);
+    assert!(!roast(2, 112, 10).critical);
+    let dodged = roast(2, 11, 9);
+    assert!(dodged.dodged && !dodged.critical);
+    assert_eq!((dodged.penalty, dodged.damage), (3, 0));
+}
+
+fn stats(wit: u8, brutality: u8, strategy: u8, mockery: u8) -> Stats {
+    Stats {
+        wit,
+        brutality,
+        strategy,
+        mockery,
+    }
+}
+
+#[test]
+fn admins_manage_the_roster() {
+    let mut contract = setup();
+    let ada = "AdaLovelace".to_string();
+    contract.add_figure(
+        ada.clone(),
+        "Ada Lovelace".to_string(),
+        stats(7, 2, 8, 3),
+        SignatureMove::Masterpiece,
+    );
+    assert_eq!(contract.get_figures(None).len(), 31);
+
+    // Running duels keep the stats and version they started with
+    let duel_id = active_duel(&mut contract, ONE);
+    set_caller(&accounts(0));
+    contract.rebalance_figure("JuliusCaesar".to_string(), stats(9, 9, 9, 9));
+    let caesar = contract.get_figure("JuliusCaesar".to_string()).unwrap();
+    assert_eq!((caesar.version, caesar.stats.wit), (2, 9));
+    let duel = &contract.duels[&duel_id.0];
+    assert_eq!((duel.version_a, duel.base_stats_a.wit), (1, 5));
+
+    contract.retire_figure(ada.clone());
+    assert_eq!(contract.get_figures(None).len(), 30);
+    assert!(!contract.get_figure(ada).unwrap().enabled);
+}
+
+#[test]
+#[should_panic(expected = "Figure is not available.")]
+fn retired_figures_cannot_be_picked() {
+    let mut contract = setup();
+    contract.retire_figure("JuliusCaesar".to_string());
+    accepted_duel(&mut contract, &roasti(), ONE, DuelOptions::default());
+}
+
+#[test]
+#[should_panic(expected = "Sender must be admin.")]
+fn only_the_admin_adds_figures() {
+    let mut contract = setup();
+    set_caller(&accounts(1));
+    contract.add_figure(
+        "AdaLovelace".to_string(),
+        "Ada Lovelace".to_string(),
+        stats(7, 2, 8, 3),
+        SignatureMove::Masterpiece,
+    );
+}
+
+#[test]
+#[should_panic(expected = "Sender must be admin.")]
+fn only_the_admin_rebalances_figures() {
+    let mut contract = setup();
+    set_caller(&accounts(1));
+    contract.rebalance_figure("JuliusCaesar".to_string(), stats(9, 9, 9, 9));
+}
+
+#[test]
+#[should_panic(expected = "Sender must be admin.")]
+fn only_the_admin_retires_figures() {
+    let mut contract = setup();
+    set_caller(&accounts(1));
+    contract.retire_figure("JuliusCaesar".to_string());
+}
+
+fn figure_hash(figure: &str, salt: &Base64VecU8) -> Base64VecU8 {
+    let length = (figure.len() as u32).to_le_bytes();
+    Base64VecU8(env::sha256(&[&length, figure.as_bytes(), &salt.0].concat()))
+}
+
+// Account 1 hides Julius Caesar and account 2 hides Sun Tzu, salted with their secrets
+fn blind_duel(contract: &mut DuelManagerContract) -> U128 {
+    let duel_id = U128(contract.next_duel_id);
+    let options = DuelOptions {
+        blind_pick: true,
+        ..Default::default()
+    };
+    let create = Msg::CreateDuel {
+        figure: None,
+        figure_hash: Some(figure_hash("JuliusCaesar", &secret(1))),
+        secret_hash: secret_hash(1),
+        options,
+        opponent: None,
+    };
+    transfer_call(contract, &roasti(), &accounts(1), ONE, create);
+    let accept = Msg::AcceptDuel {
+        duel_id,
+        figure: None,
+        figure_hash: Some(figure_hash("SunTzu", &secret(2))),
+        secret_hash: secret_hash(2),
+    };
+    transfer_call(contract, &roasti(), &accounts(2), ONE, accept);
+    duel_id
+}
+
+#[test]
+fn blind_picks_are_revealed_against_their_hash() {
+    let mut contract = setup();
+    let duel_id = blind_duel(&mut contract);
+    reveal_secrets(&mut contract, duel_id);
+    assert!(contract.duels[&duel_id.0].figure_a.is_none());
+
+    for (i, figure) in [(1, "JuliusCaesar"), (2, "SunTzu")] {
+        set_caller(&accounts(i));
+        contract.reveal_figure(duel_id, figure.to_string(), secret(i));
+    }
+    let duel = &contract.duels[&duel_id.0];
+    assert_eq!(duel.figure_a.as_deref(), Some("JuliusCaesar"));
+    assert_eq!(duel.figure_b.as_deref(), Some("SunTzu"));
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+}
+
+#[test]
+#[should_panic(expected = "Figure does not match commitment.")]
+fn blind_picks_cannot_be_swapped() {
+    let mut contract = setup();
+    let duel_id = blind_duel(&mut contract);
+    set_caller(&accounts(1));
+    contract.reveal_figure(duel_id, "GenghisKhan".to_string(), secret(1));
+}
+
+#[test]
+#[should_panic(expected = "Both figures must be chosen before taking turns.")]
+fn blind_duels_wait_for_both_figures() {
+    let mut contract = setup();
+    let duel_id = blind_duel(&mut contract);
+    reveal_secrets(&mut contract, duel_id);
+    set_caller(&accounts(1));
+    contract.reveal_figure(duel_id, "JuliusCaesar".to_string(), secret(1));
+    contract.take_turn(duel_id, RoastStyle::Witty);
+}
+
+// Accounts 1 and 2 enter a draft duel with one ban each
+fn draft_duel(contract: &mut DuelManagerContract) -> U128 {
+    let duel_id = U128(contract.next_duel_id);
+    let options = DuelOptions {
+        draft: true,
+        bans: Some(1),
+        ..Default::default()
+    };
+    let create = Msg::CreateDuel {
+        figure: None,
+        figure_hash: None,
+        secret_hash: secret_hash(1),
+        options,
+        opponent: None,
+    };
+    transfer_call(contract, &roasti(), &accounts(1), ONE, create);
+    let accept = Msg::AcceptDuel {
+        duel_id,
+        figure: None,
+        figure_hash: None,
+        secret_hash: secret_hash(2),
+    };
+    transfer_call(contract, &roasti(), &accounts(2), ONE, accept);
+    duel_id
+}
+
+fn draft(
+    contract: &mut DuelManagerContract,
+    i: usize,
+    duel_id: U128,
+    step: DraftStep,
+    figure: &str,
+) {
+    set_caller(&accounts(i));
+    match step {
+        DraftStep::Ban => contract.ban_figure(duel_id, figure.to_string()),
+        DraftStep::Pick => contract.pick_figure(duel_id, figure.to_string()),
+    }
+}
+
+#[test]
+fn drafts_alternate_bans_then_picks() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    assert!(contract.duels[&duel_id.0].draft_step() == Some((DraftStep::Ban, true)));
+    draft(&mut contract, 1, duel_id, DraftStep::Ban, "SunTzu");
+    draft(&mut contract, 2, duel_id, DraftStep::Ban, "JuliusCaesar");
+    assert!(contract.duels[&duel_id.0].draft_step() == Some((DraftStep::Pick, true)));
+    draft(&mut contract, 1, duel_id, DraftStep::Pick, "Socrates");
+    draft(&mut contract, 2, duel_id, DraftStep::Pick, "MarkTwain");
+
+    let duel = &contract.duels[&duel_id.0];
+    assert!(duel.draft_step().is_none() && duel.draft_deadline.is_none());
+    assert_eq!(duel.bans, ["SunTzu", "JuliusCaesar"]);
+    assert_eq!(duel.figure_b.as_deref(), Some("MarkTwain"));
+    reveal_secrets(&mut contract, duel_id);
+    play_turn(&mut contract, duel_id, RoastStyle::Witty);
+}
+
+#[test]
+#[should_panic(expected = "Invalid sender.")]
+fn drafts_go_in_turn() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    draft(&mut contract, 2, duel_id, DraftStep::Ban, "SunTzu");
+}
+
+#[test]
+#[should_panic(expected = "Bans are still in progress.")]
+fn drafts_ban_before_picking() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    draft(&mut contract, 1, duel_id, DraftStep::Pick, "SunTzu");
+}
+
+#[test]
+#[should_panic(expected = "Figure is banned.")]
+fn banned_figures_cannot_be_picked() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    draft(&mut contract, 1, duel_id, DraftStep::Ban, "SunTzu");
+    draft(&mut contract, 2, duel_id, DraftStep::Ban, "JuliusCaesar");
+    draft(&mut contract, 1, duel_id, DraftStep::Pick, "JuliusCaesar");
+}
+
+#[test]
+fn missed_draft_steps_forfeit_the_duel() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    draft(&mut contract, 1, duel_id, DraftStep::Ban, "SunTzu");
+    let deadline = contract.duels[&duel_id.0].draft_deadline.unwrap();
+
+    set_caller_at(&accounts(1), deadline);
+    contract.claim_timeout_win(duel_id);
+    let duel = &contract.duels[&duel_id.0];
+    assert!(matches!(duel.winner, Some(Winner::PlayerA)));
+    assert!(matches!(duel.win_reason, Some(WinReason::Forfeit)));
+}
+
+#[test]
+#[should_panic(expected = "The current draft step has not timed out.")]
+fn draft_steps_wait_for_their_deadline() {
+    let mut contract = setup();
+    let duel_id = draft_duel(&mut contract);
+    draft(&mut contract, 1, duel_id, DraftStep::Ban, "SunTzu");
+    let deadline = contract.duels[&duel_id.0].draft_deadline.unwrap();
+
+    set_caller_at(&accounts(1), deadline - 1);
+    contract.claim_timeout_win(duel_id);
+}
+
+#[test]
+fn draft_steps_keep_the_window_the_duel_was_created_with() {
+    let mut contract = setup();
+    let hour = 60 * 60 * 1_000_000_000;
+    contract.set_pick_timeouts(24 * hour, 2 * hour);
+    let duel_id = draft_duel(&mut contract);
+    set_caller(&accounts(0));
+    contract.set_pick_timeouts(24 * hour, 6 * hour);
+
+    set_caller_at(&accounts(1), hour);
+    contract.ban_figure(duel_id, "SunTzu".to_string());
+    let duel = &contract.duels[&duel_id.0];
+    assert_eq!(duel.draft_deadline, Some(3 * hour));
+    assert_eq!(duel.reveal_timeout, 24 * hour);
+}
+
+#[test]
+#[should_panic(expected = "Reveal window must be between 1 hour and 7 days.")]
+fn reveal_windows_are_at_least_an_hour() {
+    let mut contract = setup();
+    contract.set_pick_timeouts(60 * 60 * 1_000_000_000 - 1, 60 * 60 * 1_000_000_000);
+}
+
+#[test]
+#[should_panic(expected = "Draft step window must be between 1 hour and 7 days.")]
+fn draft_step_windows_are_at_most_a_week() {
+    let mut contract = setup();
+    let week = 7 * 24 * 60 * 60 * 1_000_000_000;
+    contract.set_pick_timeouts(week, week + 1);
+}
+
+#[test]
+fn any_team_member_can_cancel_an_unfilled_team_duel() {
+    let mut contract = setup();
+    let options = DuelOptions {
+        team: true,
+        turns: Some(8),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, options);
+    deposit(&mut contract, &accounts(3), ONE);
+    set_caller(&accounts(3));
+    contract.join_team(duel_id, Side::PlayerA, "Socrates".to_string());
+    assert!(contract.duels[&duel_id.0].start_time.is_none());
+
+    let accept_timeout = contract.duels[&duel_id.0].accept_timeout;
+    set_caller_at(&accounts(3), accept_timeout);
+    contract.cancel_duel(duel_id);
+    assert!(contract.get_duel(duel_id).is_none());
+    assert_eq!(total_stake(&contract, &roasti()), 0);
+    assert_eq!(get_created_receipts().len(), 3);
+}
+
+#[test]
+#[should_panic(expected = "Sender must be player.")]
+fn outsiders_cannot_cancel_team_duels() {
+    let mut contract = setup();
+    let options = DuelOptions {
+        team: true,
+        turns: Some(8),
+        ..Default::default()
+    };
+    let duel_id = accepted_duel(&mut contract, &roasti(), ONE, options);
+    let accept_timeout = contract.duels[&duel_id.0].accept_timeout;
+    set_caller_at(&accounts(3), accept_timeout);
+    contract.cancel_duel(duel_id);
+}
@@ -0,0 +1,370 @@
+use crate::*;
+
+const MAX_TOURNAMENT_PLAYERS: u8 = 32;
+
+#[near_bindgen]
+impl DuelManagerContract {
+    pub fn get_tournament(&self, tournament_id: U128) -> Option<Tournament> {
+        self.tournaments.get(&tournament_id.0).cloned()
+    }
+
+    // Newest first. Pass the last tournament id of a page as `from_id` to get the next one.
+    // Tournaments are never removed, so their ids are dense.
+    pub fn get_tournaments(&self, from_id: Option<U128>, count: usize) -> Vec<Tournament> {
+        let end = from_id.map_or(self.next_tournament_id, |from_id| from_id.0);
+        (0..end)
+            .rev()
+            .take(count)
+            .filter_map(|tournament_id| self.tournaments.get(&tournament_id).cloned())
+            .collect()
+    }
+
+    pub fn create_tournament(
+        &mut self,
+        entry_fee: U128,
+        max_players: u8,
+        prize_split: Vec<u8>,
+        options: Option<DuelOptions>,
+    ) -> U128 {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+        assert!(
+            (2..=MAX_TOURNAMENT_PLAYERS).contains(&max_players) && max_players.is_power_of_two(),
+            "Player count must be a power of two up to {}.",
+            MAX_TOURNAMENT_PLAYERS
+        );
+
+        // One rank per bracket round plus the champion
+        let ranks = max_players.trailing_zeros() as usize + 1;
+        assert!(
+            !prize_split.is_empty() && prize_split.len() <= ranks,
+            "Prize split must cover between 1 and {} ranks.",
+            ranks
+        );
+        assert!(
+            prize_split.iter().map(|share| *share as u32).sum::<u32>() == 100,
+            "Prize split must add up to 100."
+        );
+
+        let options = options.unwrap_or_default();
+        self.assert_duel_options(&options);
+        assert!(
+            !options.require_cards,
+            "Tournaments cannot require figure cards."
+        );
+        assert!(
+            !options.blind_pick && !options.draft && !options.team,
+            "Tournaments cannot use blind picks, drafts or teams."
+        );
+        assert!(
+            options.token.is_none(),
+            "Tournaments are only played in the default token."
+        );
+
+        let tournament_id = self.next_tournament_id;
+        let tournament = Tournament {
+            id: U128(tournament_id),
+            creation_time: env::block_timestamp(),
+            entry_fee,
+            max_players,
+            prize_split,
+            options,
+            entrants: Vec::new(),
+            brackets: Vec::new(),
+            placings: Vec::new(),
+            status: TournamentStatus::Registration,
+        };
+
+        self.tournaments.insert(tournament_id, tournament);
+        self.next_tournament_id += 1;
+
+        env::log_str(&format!("Tournament {} created.", tournament_id));
+        U128(tournament_id)
+    }
+
+    #[payable]
+    pub fn join_tournament(&mut self, tournament_id: U128, figure: FigureId) {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let sender = env::predecessor_account_id();
+        self._join_tournament(sender, tournament_id, figure);
+    }
+
+    // A stalled bracket duel holds up the whole tournament, so once its deadline passes anyone
+    // can settle it. The side owing the next move loses. If both still owe the same secret, the
+    // duel counts as a draw, which advances the higher rated player.
+    #[payable]
+    pub fn settle_bracket_timeout(&mut self, duel_id: U128) -> Option<Promise> {
+        assert!(
+            env::attached_deposit() == NearToken::from_yoctonear(1),
+            "This function requires exactly 1 yoctoNEAR to be attached for security purposes."
+        );
+
+        let duel = self.duels.get(&duel_id.0).expect("Duel not found.").clone();
+        assert!(
+            duel.tournament_id.is_some(),
+            "Duel is not part of a tournament."
+        );
+        assert!(duel.winner.is_none(), "Duel already completed.");
+        assert!(
+            env::block_timestamp() >= duel.deadline.unwrap(),
+            "The players still have time to move."
+        );
+
+        let winner = match duel.waiting_on() {
+            Some(true) => Winner::PlayerB,
+            Some(false) => Winner::PlayerA,
+            None => Winner::Draw,
+        };
+        env::log_str(&format!(
+            "Bracket duel {} settled after the deadline passed.",
+            duel_id.0
+        ));
+        self.settle_duel(duel, winner, WinReason::Timeout)
+    }
+
+    pub fn cancel_tournament(&mut self, tournament_id: U128) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.admin_id, "Sender must be admin.");
+
+        let tournament = self
+            .tournaments
+            .get_mut(&tournament_id.0)
+            .expect("Tournament not found.");
+        assert!(
+            tournament.status == TournamentStatus::Registration,
+            "Only tournaments in registration can be canceled."
+        );
+
+        // Entry fees go back to each entrant's balance
+        for entrant in tournament.entrants.iter() {
+            *self
+                .stakes
+                .entry((entrant.account_id.clone(), self.ft_contract.clone()))
+                .or_insert(0) += tournament.entry_fee.0;
+        }
+        tournament.status = TournamentStatus::Canceled;
+
+        env::log_str(&format!(
+            "Tournament {} canceled. Entry fees refunded.",
+            tournament_id.0
+        ));
+    }
+
+    pub(crate) fn _join_tournament(
+        &mut self,
+        sender: AccountId,
+        tournament_id: U128,
+        figure: FigureId,
+    ) {
+        self.assert_figure(&figure);
+        let tournament = self
+            .tournaments
+            .get_mut(&tournament_id.0)
+            .expect("Tournament not found.");
+        let balance = self
+            .stakes
+            .entry((sender.clone(), self.ft_contract.clone()))
+            .or_insert(0);
+
+        assert!(
+            tournament.status == TournamentStatus::Registration,
+            "Tournament registration is closed."
+        );
+        assert!(
+            tournament.entrant(&sender).is_none(),
+            "Account is already participating."
+        );
+        assert!(*balance >= tournament.entry_fee.0, "Insufficient balance");
+
+        *balance -= tournament.entry_fee.0;
+        tournament.entrants.push(Entrant {
+            account_id: sender.clone(),
+            figure,
+        });
+
+        env::log_str(&format!(
+            "{} joined tournament {}.",
+            sender, tournament_id.0
+        ));
+
+        if tournament.entrants.len() == tournament.max_players as usize {
+            self.start_tournament(tournament_id.0);
+        }
+    }
+
+    // Called whenever a bracket duel settles. Builds the next round once every duel
+    // of the current one is decided, and pays out when the final is over.
+    pub(crate) fn advance_tournament(&mut self, tournament_id: U128) -> Option<Promise> {
+        let mut tournament = self
+            .tournaments
+            .get(&tournament_id.0)
+            .expect("Tournament not found.")
+            .clone();
+
+        let winners: Option<Vec<AccountId>> = tournament
+            .brackets
+            .last()
+            .unwrap()
+            .iter()
+            .map(|duel_id| {
+                self.duels
+                    .get(&duel_id.0)
+                    .and_then(|duel| self.bracket_winner(duel))
+            })
+            .collect();
+        let winners = winners?;
+
+        if winners.len() == 1 {
+            let promise = self.finish_tournament(&mut tournament);
+            self.tournaments.insert(tournament_id.0, tournament);
+            return promise;
+        }
+
+        let next_round = winners
+            .chunks(2)
+            .map(|pair| {
+                let a = tournament.entrant(&pair[0]).unwrap().clone();
+                let b = tournament.entrant(&pair[1]).unwrap().clone();
+                self.create_bracket_duel(&tournament, &a, &b)
+            })
+            .collect();
+        tournament.brackets.push(next_round);
+
+        env::log_str(&format!(
+            "Tournament {} advanced to round {}.",
+            tournament_id.0,
+            tournament.brackets.len()
+        ));
+        self.tournaments.insert(tournament_id.0, tournament);
+        None
+    }
+
+    fn start_tournament(&mut self, tournament_id: u128) {
+        let mut tournament = self.tournaments.get(&tournament_id).unwrap().clone();
+
+        // Shuffle the seeding so join order doesn't decide the bracket
+        let seed = env::random_seed();
+        let mut entrants = tournament.entrants.clone();
+        for i in (1..entrants.len()).rev() {
+            let j = seed[i % seed.len()] as usize % (i + 1);
+            entrants.swap(i, j);
+        }
+
+        let first_round = entrants
+            .chunks(2)
+            .map(|pair| self.create_bracket_duel(&tournament, &pair[0], &pair[1]))
+            .collect();
+        tournament.brackets.push(first_round);
+        tournament.status = TournamentStatus::InProgress;
+
+        env::log_str(&format!("Tournament {} started.", tournament_id));
+        self.tournaments.insert(tournament_id, tournament);
+    }
+
+    fn create_bracket_duel(&mut self, tournament: &Tournament, a: &Entrant, b: &Entrant) -> U128 {
+        let duel_id = self.next_duel_id;
+        let mut duel = Duel::new(
+            duel_id,
+            U128(0),
+            self.ft_contract.clone(),
+            a.account_id.clone(),
+            Pick::Open(a.figure.clone()),
+            &tournament.options,
+            &self.config,
+        );
+        duel.tournament_id = Some(tournament.id);
+        self.start_duel(&mut duel, b.account_id.clone(), b.figure.clone());
+
+        self.index_duel(&duel, None);
+        self.duels.insert(duel_id, duel);
+        self.next_duel_id += 1;
+
+        env::log_str(&format!(
+            "Duel {} created for tournament {}.",
+            duel_id, tournament.id.0
+        ));
+        U128(duel_id)
+    }
+
+    fn finish_tournament(&mut self, tournament: &mut Tournament) -> Option<Promise> {
+        // Rank 0 is the champion, rank 1 the runner-up, rank 2 the semi-final losers...
+        let mut ranks: Vec<Vec<AccountId>> = Vec::new();
+        for round in tournament.brackets.iter().rev() {
+            let duels: Vec<&Duel> = round
+                .iter()
+                .map(|duel_id| self.duels.get(&duel_id.0).unwrap())
+                .collect();
+            if ranks.is_empty() {
+                ranks.push(vec![self.bracket_winner(duels[0]).unwrap()]);
+            }
+            ranks.push(duels.iter().map(|d| self.bracket_loser(d)).collect());
+        }
+
+        let token = self.ft_contract.clone();
+        let pool = tournament.prize_pool();
+        let mut paid = 0;
+        let mut promise = None;
+        for (rank, share) in tournament.prize_split.iter().enumerate().skip(1) {
+            let players = &ranks[rank];
+            let amount = pool * *share as u128 / 100 / players.len() as u128;
+            if amount == 0 {
+                continue;
+            }
+            for player in players {
+                promise = Some(self.transfer(&token, player.clone(), U128(amount)));
+                paid += amount;
+            }
+        }
+
+        // The champion also keeps any rounding dust
+        let champion = ranks[0][0].clone();
+        if pool > paid {
+            promise = Some(self.transfer(&token, champion.clone(), U128(pool - paid)));
+        }
+
+        tournament.placings = ranks.into_iter().flatten().collect();
+        tournament.status = TournamentStatus::Finished;
+
+        env::log_str(&format!(
+            "Tournament {} finished! Result: {} won!",
+            tournament.id.0, champion
+        ));
+        promise
+    }
+
+    fn bracket_winner(&self, duel: &Duel) -> Option<AccountId> {
+        duel.winner?;
+        if self.advances_a(duel) {
+            Some(duel.player_a.clone())
+        } else {
+            duel.player_b.clone()
+        }
+    }
+
+    fn bracket_loser(&self, duel: &Duel) -> AccountId {
+        if self.advances_a(duel) {
+            duel.player_b.clone().unwrap()
+        } else {
+            duel.player_a.clone()
+        }
+    }
+
+    // Drawn bracket duels go to whoever dealt more damage, then to the higher rating. Only a
+    // draw tied on both advances player A, who was placed there by the random seeding.
+    fn advances_a(&self, duel: &Duel) -> bool {
+        match duel.winner {
+            Some(Winner::PlayerA) => true,
+            Some(Winner::PlayerB) => false,
+            _ => {
+                let (damage_a, damage_b) = duel.total_damage();
+                let rating_a = self.get_rating(duel.player_a.clone());
+                let rating_b = self.get_rating(duel.player_b.clone().unwrap());
+                (damage_a, rating_a) >= (damage_b, rating_b)
+            }
+        }
+    }
+}
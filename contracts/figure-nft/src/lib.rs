@@ -0,0 +1,364 @@
+/*!
+Figure card NFTs for the duel manager.
+NOTES:
+  - Every card holds one historical figure, named the same way the duel manager serializes it
+    (e.g. "SunTzu"). The duel manager asks `owns_figure` before letting a player field a figure
+    in a duel that requires cards.
+  - Cards are minted by transferring $ROASTI with `ft_transfer_call`. The price is burned and any
+    excess is refunded to the sender.
+  - The contract pays for the storage of minted cards out of its own balance.
+*/
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::core::{
+    NonFungibleTokenCore, NonFungibleTokenResolver,
+};
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+use near_contract_standards::non_fungible_token::metadata::{
+    NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
+};
+use near_contract_standards::non_fungible_token::{NonFungibleToken, Token, TokenId};
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::collections::{LazyOption, LookupMap};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, ext_contract, near, require, serde_json, AccountId, BorshStorageKey, NearToken,
+    PanicOnDefault, PromiseOrValue,
+};
+use std::collections::HashMap;
+
+#[derive(PanicOnDefault)]
+#[near(contract_state)]
+pub struct FigureNftContract {
+    owner_id: AccountId,
+    ft_contract: AccountId,
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    figures: LookupMap<TokenId, String>,
+    card_prices: LookupMap<String, U128>,
+    next_token_id: u64,
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(crate = "near_sdk::borsh")]
+enum StorageKey {
+    NonFungibleToken,
+    Metadata,
+    TokenMetadata,
+    Enumeration,
+    Figures,
+    CardPrices,
+}
+
+#[near(serializers = [json])]
+#[serde(tag = "function", rename_all = "snake_case")]
+pub enum Msg {
+    MintCard { figure: String },
+}
+
+#[allow(dead_code)]
+#[ext_contract(ext_ft_contract)]
+trait FtContract {
+    fn ft_burn(&mut self, amount: U128);
+}
+
+#[near]
+impl FigureNftContract {
+    #[init]
+    pub fn new_default_meta(owner_id: AccountId, ft_contract: AccountId) -> Self {
+        Self::new(
+            owner_id,
+            ft_contract,
+            NFTContractMetadata {
+                spec: NFT_METADATA_SPEC.to_string(),
+                name: "Venividiroasti Figures".to_string(),
+                symbol: "ROASTFIG".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+        )
+    }
+
+    #[init]
+    pub fn new(owner_id: AccountId, ft_contract: AccountId, metadata: NFTContractMetadata) -> Self {
+        require!(!env::state_exists(), "Already initialized");
+        metadata.assert_valid();
+        Self {
+            tokens: NonFungibleToken::new(
+                StorageKey::NonFungibleToken,
+                owner_id.clone(),
+                Some(StorageKey::TokenMetadata),
+                Some(StorageKey::Enumeration),
+                None::<StorageKey>,
+            ),
+            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            owner_id,
+            ft_contract,
+            figures: LookupMap::new(StorageKey::Figures),
+            card_prices: LookupMap::new(StorageKey::CardPrices),
+            next_token_id: 0,
+        }
+    }
+
+    pub fn get_card_price(&self, figure: String) -> Option<U128> {
+        self.card_prices.get(&figure)
+    }
+
+    pub fn get_card_figure(&self, token_id: TokenId) -> Option<String> {
+        self.figures.get(&token_id)
+    }
+
+    pub fn owns_figure(&self, account_id: AccountId, figure: String) -> bool {
+        let tokens = self.tokens.tokens_per_owner.as_ref().unwrap();
+        tokens.get(&account_id).is_some_and(|token_ids| {
+            token_ids
+                .iter()
+                .any(|token_id| self.figures.get(&token_id).as_ref() == Some(&figure))
+        })
+    }
+
+    // A price of `None` takes the figure off sale
+    pub fn set_card_price(&mut self, figure: String, price: Option<U128>) {
+        let sender = env::predecessor_account_id();
+        assert!(sender == self.owner_id, "Sender must be owner.");
+
+        match price {
+            Some(price) => {
+                assert!(price.0 > 0, "Price must be positive.");
+                self.card_prices.insert(&figure, &price);
+            }
+            None => {
+                self.card_prices.remove(&figure);
+            }
+        }
+    }
+
+    fn mint_card(&mut self, owner_id: AccountId, figure: String) -> Token {
+        let token_id = self.next_token_id.to_string();
+        self.next_token_id += 1;
+        self.figures.insert(&token_id, &figure);
+
+        let metadata = TokenMetadata {
+            title: Some(figure.clone()),
+            description: Some(format!("A {} card for Venividiroasti duels.", figure)),
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: Some(env::block_timestamp_ms().to_string()),
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        };
+        self.tokens
+            .internal_mint_with_refund(token_id, owner_id, Some(metadata), None)
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for FigureNftContract {
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert!(
+            env::predecessor_account_id() == self.ft_contract,
+            "The token is not supported"
+        );
+
+        let Msg::MintCard { figure } = serde_json::from_str::<Msg>(&msg).expect("Invalid message.");
+        let price = self
+            .card_prices
+            .get(&figure)
+            .expect("Figure is not for sale.");
+        assert!(amount.0 >= price.0, "Insufficient amount.");
+
+        let token = self.mint_card(sender_id.clone(), figure.clone());
+        ext_ft_contract::ext(self.ft_contract.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .ft_burn(price);
+
+        env::log_str(&format!(
+            "Card {} of {} minted for {}.",
+            token.token_id, figure, sender_id
+        ));
+        PromiseOrValue::Value(U128(amount.0 - price.0))
+    }
+}
+
+#[near]
+impl NonFungibleTokenCore for FigureNftContract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        self.tokens
+            .nft_transfer(receiver_id, token_id, approval_id, memo);
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        self.tokens
+            .nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.tokens.nft_token(token_id)
+    }
+}
+
+#[near]
+impl NonFungibleTokenResolver for FigureNftContract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.tokens.nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id,
+            token_id,
+            approved_account_ids,
+        )
+    }
+}
+
+#[near]
+impl NonFungibleTokenEnumeration for FigureNftContract {
+    fn nft_total_supply(&self) -> U128 {
+        self.tokens.nft_total_supply()
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens.nft_tokens(from_index, limit)
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        self.tokens.nft_supply_for_owner(account_id)
+    }
+
+    fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        self.tokens
+            .nft_tokens_for_owner(account_id, from_index, limit)
+    }
+}
+
+#[near]
+impl NonFungibleTokenMetadataProvider for FigureNftContract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const PRICE: u128 = 1_000_000;
+
+    fn owner() -> AccountId {
+        accounts(0)
+    }
+
+    fn ft_contract() -> AccountId {
+        accounts(1)
+    }
+
+    fn user() -> AccountId {
+        accounts(2)
+    }
+
+    fn mint_msg(figure: &str) -> String {
+        format!(
+            "{{\"function\": \"mint_card\", \"figure\": \"{}\"}}",
+            figure
+        )
+    }
+
+    fn setup() -> (FigureNftContract, VMContextBuilder) {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(owner()).build());
+
+        let mut contract = FigureNftContract::new_default_meta(owner(), ft_contract());
+        contract.set_card_price("SunTzu".to_string(), Some(U128(PRICE)));
+
+        (contract, context)
+    }
+
+    #[test]
+    fn test_mint_card() {
+        let (mut contract, mut context) = setup();
+
+        testing_env!(context.predecessor_account_id(ft_contract()).build());
+        let unused = contract.ft_on_transfer(user(), U128(PRICE + 5), mint_msg("SunTzu"));
+
+        assert!(matches!(unused, PromiseOrValue::Value(U128(5))));
+        assert_eq!(contract.nft_supply_for_owner(user()).0, 1);
+        assert_eq!(contract.get_card_figure("0".to_string()).unwrap(), "SunTzu");
+        assert!(contract.owns_figure(user(), "SunTzu".to_string()));
+        assert!(!contract.owns_figure(user(), "GenghisKhan".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient amount.")]
+    fn test_mint_card_panics_on_low_amount() {
+        let (mut contract, mut context) = setup();
+
+        testing_env!(context.predecessor_account_id(ft_contract()).build());
+        contract.ft_on_transfer(user(), U128(PRICE - 1), mint_msg("SunTzu"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Figure is not for sale.")]
+    fn test_mint_card_panics_on_unlisted_figure() {
+        let (mut contract, mut context) = setup();
+
+        testing_env!(context.predecessor_account_id(ft_contract()).build());
+        contract.ft_on_transfer(user(), U128(PRICE), mint_msg("GenghisKhan"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The token is not supported")]
+    fn test_mint_card_panics_on_other_token() {
+        let (mut contract, mut context) = setup();
+
+        testing_env!(context.predecessor_account_id(user()).build());
+        contract.ft_on_transfer(user(), U128(PRICE), mint_msg("SunTzu"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sender must be owner.")]
+    fn test_set_card_price_panics_on_non_owner() {
+        let (mut contract, mut context) = setup();
+
+        testing_env!(context.predecessor_account_id(user()).build());
+        contract.set_card_price("GenghisKhan".to_string(), Some(U128(PRICE)));
+    }
+}
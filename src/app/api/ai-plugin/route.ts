@@ -32,7 +32,7 @@ export async function GET() {
         //   When processing blockchain transactions, first generate a transaction payload, then explicitly use the 'generate-transaction' tool for NEAR to complete the transaction.
         //   Simply retrieving the payload is not enough—users must execute the transaction via the corresponding tool.
         //   When dealing with $ROASTI amounts, keep in mind that the token has 24 decimals.
-        //   When inputting a figure parameter, ensure they available in the /api/tools/get-figures endpoint. Format as an enum variant with no spaces or punctuation.
+        //   When inputting a figure parameter, ensure they available in the /api/tools/get-figures endpoint. Use the figure's id, which has no spaces or punctuation.
         //   When inputting a roast style parameter, ensure it is available in the /api/tools/get-styles endpoint. Format as an enum variant with no spaces or punctuation.
         //   When asked to view leaderboards, use /api/tools/get-leaderboard-by-wins and /api/tools/get-leaderboard-by-damage
         //   When calling /api/tools/create-duel, the minimum stake is 1 $ROASTI (with 24 decimals).
@@ -44,9 +44,10 @@ export async function GET() {
           When processing blockchain transactions, first generate a transaction payload, then explicitly use the 'generate-transaction' tool for NEAR to complete the transaction.
           Simply retrieving the payload is not enough—users must execute the transaction via the corresponding tool.
           When dealing with $ROASTI amounts, keep in mind that the token has 24 decimals.
-          When inputting a figure parameter, ensure they available in the /api/tools/get-figures endpoint. Format as an enum variant with no spaces or punctuation.
+          When inputting a figure parameter, ensure they available in the /api/tools/get-figures endpoint. Use the figure's id, which has no spaces or punctuation.
           When inputting a roast style parameter, ensure it is available in the /api/tools/get-styles endpoint. Format as an enum variant with no spaces or punctuation.
           When asked to view leaderboards, use /api/tools/get-leaderboard-by-wins and /api/tools/get-leaderboard-by-damage
+          When creating, accepting or committing to a duel, show the player the returned secret and tell them to keep it. Once the duel is accepted, both players reveal it with /api/tools/reveal-secret before taking turns.
           When calling the 'ft_transfer_call' method, ensure it is calling 'token.venividiroasti.near', NOT 'duels.venividiroasti.near'.
           Large datasets should be formatted as tables for readability.
         `,
@@ -67,7 +68,7 @@ export async function GET() {
           operationId: "create-duel",
           summary: "Initiate a duel",
           description:
-            "Starts a new duel by selecting a historical figure and staking $ROASTI.",
+            "Starts a new duel by selecting a historical figure and staking $ROASTI. Returns a secret in hex, which the player must keep to reveal once the duel is accepted.",
           parameters: [
             {
               name: "figure",
@@ -116,7 +117,7 @@ export async function GET() {
           operationId: "accept-duel",
           summary: "Join an existing duel",
           description:
-            "A second player joins a duel by selecting a figure and matching the stake.",
+            "A second player joins a duel by selecting a figure and matching the stake. Returns a secret in hex, which the player must keep to reveal right after.",
           parameters: [
             {
               name: "duelId",
@@ -183,6 +184,16 @@ export async function GET() {
               },
               description: "The type of roast attack chosen for the turn.",
             },
+            {
+              name: "signature",
+              in: "query",
+              required: false,
+              schema: {
+                type: "boolean",
+              },
+              description:
+                "Use the figure's signature move for this turn. Each player can use it once per duel.",
+            },
           ],
           responses: {
             "200": { description: "Turn processed successfully." },
@@ -210,7 +221,7 @@ export async function GET() {
           operationId: "cancel-duel",
           summary: "Cancel an inactive duel",
           description:
-            "Cancels a duel if unaccepted for 24h or inactive for 48h.",
+            "Cancels a duel if unaccepted for 24h, or if both players stopped before revealing their secrets. A player left waiting on their opponent should claim a timeout win instead.",
           parameters: [
             {
               name: "duelId",
@@ -243,12 +254,221 @@ export async function GET() {
           },
         },
       },
+      "/api/tools/commit-secret": {
+        post: {
+          operationId: "commit-secret",
+          summary: "Commit a secret to a duel",
+          description:
+            "Commits a new secret to a duel started without one, such as a tournament bracket or matchmaking duel. Returns the secret in hex, which the player must keep to reveal later.",
+          parameters: [
+            {
+              name: "duelId",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The ID of the duel.",
+            },
+          ],
+          responses: {
+            "200": { description: "Secret committed successfully." },
+            "400": {
+              description: "Invalid request or missing parameters.",
+              content: {
+                "application/json": {
+                  schema: {
+                    type: "object",
+                    properties: {
+                      error: {
+                        type: "string",
+                        description: "Error message.",
+                      },
+                    },
+                  },
+                },
+              },
+            },
+          },
+        },
+      },
+      "/api/tools/reveal-secret": {
+        post: {
+          operationId: "reveal-secret",
+          summary: "Reveal a duel secret",
+          description:
+            "Reveals the secret committed when creating, accepting or committing to a duel. Both players reveal once the duel is accepted, and turns begin when both secrets are in.",
+          parameters: [
+            {
+              name: "duelId",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The ID of the duel.",
+            },
+            {
+              name: "secret",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The 32-byte secret in hex, as returned when the duel was created, accepted or committed to.",
+            },
+          ],
+          responses: {
+            "200": { description: "Secret revealed successfully." },
+            "400": {
+              description: "Invalid request or missing parameters.",
+              content: {
+                "application/json": {
+                  schema: {
+                    type: "object",
+                    properties: {
+                      error: {
+                        type: "string",
+                        description: "Error message.",
+                      },
+                    },
+                  },
+                },
+              },
+            },
+          },
+        },
+      },
+      "/api/tools/claim-timeout-win": {
+        post: {
+          operationId: "claim-timeout-win",
+          summary: "Claim a win on timeout",
+          description:
+            "Wins a duel whose opponent missed a deadline for a secret, a turn, a draft step or a blind pick reveal, or ran out of time on their clock.",
+          parameters: [
+            {
+              name: "duelId",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The ID of the duel.",
+            },
+          ],
+          responses: {
+            "200": { description: "Timeout win claimed successfully." },
+            "400": {
+              description: "Invalid request or missing parameters.",
+              content: {
+                "application/json": {
+                  schema: {
+                    type: "object",
+                    properties: {
+                      error: {
+                        type: "string",
+                        description: "Error message.",
+                      },
+                    },
+                  },
+                },
+              },
+            },
+          },
+        },
+      },
+      "/api/tools/surrender": {
+        post: {
+          operationId: "surrender",
+          summary: "Surrender a duel",
+          description:
+            "Concedes a duel. The opponent is paid out as for any other win.",
+          parameters: [
+            {
+              name: "duelId",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The ID of the duel.",
+            },
+          ],
+          responses: {
+            "200": { description: "Duel surrendered successfully." },
+            "400": {
+              description: "Invalid request or missing parameters.",
+              content: {
+                "application/json": {
+                  schema: {
+                    type: "object",
+                    properties: {
+                      error: {
+                        type: "string",
+                        description: "Error message.",
+                      },
+                    },
+                  },
+                },
+              },
+            },
+          },
+        },
+      },
+      "/api/tools/claim-bet": {
+        post: {
+          operationId: "claim-bet",
+          summary: "Claim a bet",
+          description:
+            "Claims a bet on a settled duel. Winnings and refunds go to the bettor's balance in the duel manager.",
+          parameters: [
+            {
+              name: "duelId",
+              in: "query",
+              required: true,
+              schema: {
+                type: "string",
+              },
+              description: "The ID of the duel.",
+            },
+          ],
+          responses: {
+            "200": { description: "Bet claimed successfully." },
+            "400": {
+              description: "Invalid request or missing parameters.",
+              content: {
+                "application/json": {
+                  schema: {
+                    type: "object",
+                    properties: {
+                      error: {
+                        type: "string",
+                        description: "Error message.",
+                      },
+                    },
+                  },
+                },
+              },
+            },
+          },
+        },
+      },
       "/api/tools/get-figures": {
         get: {
           operationId: "get-figures",
           summary: "Fetch available historical figures",
           description:
             "Returns a list of all historical figures that can be selected for duels, including their stats.",
+          parameters: [
+            {
+              name: "accountId",
+              in: "query",
+              required: false,
+              schema: { type: "string" },
+              description:
+                "NEAR account ID of a player. Stats then include the level bonuses this player has earned with each figure.",
+            },
+          ],
           responses: {
             "200": {
               description: "Successful response with historical figures",
@@ -262,9 +482,24 @@ export async function GET() {
                         items: {
                           type: "object",
                           properties: {
+                            id: {
+                              type: "string",
+                              description:
+                                "The id of the historical figure, used when selecting it.",
+                            },
                             name: {
                               type: "string",
-                              description: "The name of the historical figure.",
+                              description: "The display name of the historical figure.",
+                            },
+                            version: {
+                              type: "integer",
+                              description:
+                                "The stat version, bumped whenever the figure is rebalanced.",
+                            },
+                            level: {
+                              type: "integer",
+                              description:
+                                "The player's level with this figure (0 without an account).",
                             },
                             wit: {
                               type: "integer",
@@ -284,6 +519,7 @@ export async function GET() {
                             },
                           },
                           required: [
+                            "id",
                             "name",
                             "wit",
                             "brutality",
@@ -482,11 +718,12 @@ export async function GET() {
               description: "The number of finished duels to retrieve.",
             },
             {
-              name: "offset",
+              name: "fromId",
               in: "query",
               required: false,
-              schema: { type: "integer", default: 0 },
-              description: "Offset for pagination.",
+              schema: { type: "string" },
+              description:
+                "Id of the last duel from the previous page. Results are newest first and start below this id.",
             },
           ],
           responses: {
@@ -586,11 +823,12 @@ export async function GET() {
               description: "The number of active duels to retrieve.",
             },
             {
-              name: "offset",
+              name: "fromId",
               in: "query",
               required: false,
-              schema: { type: "integer", default: 0 },
-              description: "Offset for pagination.",
+              schema: { type: "string" },
+              description:
+                "Id of the last duel from the previous page. Results are newest first and start below this id.",
             },
           ],
           responses: {
@@ -690,11 +928,12 @@ export async function GET() {
               description: "The number of finished duels to retrieve.",
             },
             {
-              name: "offset",
+              name: "fromId",
               in: "query",
               required: false,
-              schema: { type: "integer", default: 0 },
-              description: "Offset for pagination.",
+              schema: { type: "string" },
+              description:
+                "Id of the last duel from the previous page. Results are newest first and start below this id.",
             },
           ],
           responses: {
@@ -792,6 +1031,21 @@ export async function GET() {
               schema: { type: "string" },
               description: "The NEAR account ID of the player.",
             },
+            {
+              name: "count",
+              in: "query",
+              required: false,
+              schema: { type: "integer", default: 10 },
+              description: "The number of duels to retrieve.",
+            },
+            {
+              name: "fromId",
+              in: "query",
+              required: false,
+              schema: { type: "string" },
+              description:
+                "Id of the last duel from the previous page. Results are newest first and start below this id.",
+            },
           ],
           responses: {
             "200": {
@@ -890,11 +1144,12 @@ export async function GET() {
               description: "The number of top players to retrieve.",
             },
             {
-              name: "offset",
+              name: "fromAccount",
               in: "query",
               required: false,
-              schema: { type: "integer", default: 0 },
-              description: "Offset for pagination.",
+              schema: { type: "string" },
+              description:
+                "Account id of the last player from the previous page. Results are highest first and continue below this player.",
             },
           ],
           responses: {
@@ -955,11 +1210,12 @@ export async function GET() {
               description: "The number of top players to retrieve.",
             },
             {
-              name: "offset",
+              name: "fromAccount",
               in: "query",
               required: false,
-              schema: { type: "integer", default: 0 },
-              description: "Offset for pagination.",
+              schema: { type: "string" },
+              description:
+                "Account id of the last player from the previous page. Results are highest first and continue below this player.",
             },
           ],
           responses: {
@@ -1,4 +1,5 @@
 import { NextResponse } from "next/server";
+import { createHash, randomBytes } from "crypto";
 import { providers } from "near-api-js";
 
 const TOKEN_CONTRACT_ID = process.env.TOKEN_CONTRACT_ID!;
@@ -36,6 +37,10 @@ export async function POST(request: Request) {
     );
     const stake = duel.stake;
 
+    // The player keeps the secret and reveals it once the duel is accepted
+    const secret = randomBytes(32);
+    const secretHash = createHash("sha256").update(secret).digest("base64");
+
     const transactionPayload = {
       actions: [
         {
@@ -51,6 +56,7 @@ export async function POST(request: Request) {
                 function: "accept_duel",
                 duel_id: duelId,
                 figure,
+                secret_hash: secretHash,
               }),
             },
             deposit: "1",
@@ -60,7 +66,10 @@ export async function POST(request: Request) {
       ],
     };
 
-    return NextResponse.json({ transactionPayload });
+    return NextResponse.json({
+      transactionPayload,
+      secret: secret.toString("hex"),
+    });
   } catch (error) {
     console.error("Error generating NEAR transaction payload:", error);
     return NextResponse.json(
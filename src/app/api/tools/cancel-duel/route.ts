@@ -24,6 +24,7 @@ export async function POST(request: Request) {
             args: {
               duel_id: duelId,
             },
+            deposit: "1",
             gas: "30000000000000",
           },
         },
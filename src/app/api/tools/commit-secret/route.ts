@@ -0,0 +1,52 @@
+import { NextResponse } from "next/server";
+import { createHash, randomBytes } from "crypto";
+
+const DUELS_CONTRACT_ID = process.env.DUELS_CONTRACT_ID!;
+
+export async function POST(request: Request) {
+  try {
+    const { searchParams } = new URL(request.url);
+    const duelId = searchParams.get("duelId");
+
+    if (!duelId) {
+      return NextResponse.json(
+        { error: "duelId is a required parameter" },
+        { status: 400 },
+      );
+    }
+
+    // Duels started by the contract, such as tournament brackets and matchmaking, begin
+    // without commitments. The player keeps the secret and reveals it afterwards.
+    const secret = randomBytes(32);
+    const secretHash = createHash("sha256").update(secret).digest("base64");
+
+    const transactionPayload = {
+      actions: [
+        {
+          type: "FunctionCall",
+          params: {
+            account_id: DUELS_CONTRACT_ID,
+            methodName: "commit_secret",
+            args: {
+              duel_id: duelId,
+              secret_hash: secretHash,
+            },
+            deposit: "1",
+            gas: "30000000000000",
+          },
+        },
+      ],
+    };
+
+    return NextResponse.json({
+      transactionPayload,
+      secret: secret.toString("hex"),
+    });
+  } catch (error) {
+    console.error("Error generating NEAR transaction payload:", error);
+    return NextResponse.json(
+      { error: "Failed to generate NEAR transaction payload" },
+      { status: 500 },
+    );
+  }
+}
@@ -1,4 +1,5 @@
 import { NextResponse } from "next/server";
+import { createHash, randomBytes } from "crypto";
 
 const TOKEN_CONTRACT_ID = process.env.TOKEN_CONTRACT_ID!;
 const DUELS_CONTRACT_ID = process.env.DUELS_CONTRACT_ID!;
@@ -16,6 +17,10 @@ export async function POST(request: Request) {
       );
     }
 
+    // The player keeps the secret and reveals it once the duel is accepted
+    const secret = randomBytes(32);
+    const secretHash = createHash("sha256").update(secret).digest("base64");
+
     const transactionPayload = {
       actions: [
         {
@@ -30,6 +35,7 @@ export async function POST(request: Request) {
               msg: JSON.stringify({
                 function: "create_duel",
                 figure,
+                secret_hash: secretHash,
               }),
             },
             deposit: "1",
@@ -39,7 +45,10 @@ export async function POST(request: Request) {
       ],
     };
 
-    return NextResponse.json({ transactionPayload });
+    return NextResponse.json({
+      transactionPayload,
+      secret: secret.toString("hex"),
+    });
   } catch (error) {
     console.error("Error generating NEAR transaction payload:", error);
     return NextResponse.json(
@@ -7,12 +7,16 @@ export async function GET(request: Request) {
   try {
     const { searchParams } = new URL(request.url);
     const accountId = searchParams.get("accountId");
+    const fromId = searchParams.get("fromId");
+    const count = searchParams.get("count");
 
     const url = `https://rpc.mainnet.near.org`;
     const provider = new providers.JsonRpcProvider({ url });
 
     const args = {
       account_id: accountId,
+      from_id: fromId,
+      count: parseInt(count || "10"),
     };
 
     const res = await provider.query({
@@ -7,14 +7,14 @@ export async function GET(request: Request) {
   try {
     const { searchParams } = new URL(request.url);
     const count = searchParams.get("count");
-    const offset = searchParams.get("offset");
+    const fromId = searchParams.get("fromId");
 
     const url = `https://rpc.mainnet.near.org`;
     const provider = new providers.JsonRpcProvider({ url });
 
     const args = {
+      from_id: fromId,
       count: parseInt(count || "10"),
-      offset: parseInt(offset || "0"),
     };
 
     const res = await provider.query({
@@ -3,12 +3,17 @@ import { providers } from "near-api-js";
 
 const DUELS_CONTRACT_ID = process.env.DUELS_CONTRACT_ID!;
 
-export async function GET() {
+export async function GET(request: Request) {
   try {
+    const { searchParams } = new URL(request.url);
+    const accountId = searchParams.get("accountId");
+
     const url = `https://rpc.mainnet.near.org`;
     const provider = new providers.JsonRpcProvider({ url });
 
-    const args = {};
+    const args = {
+      account_id: accountId,
+    };
 
     const res = await provider.query({
       request_type: "call_function",
@@ -6,15 +6,15 @@ const DUELS_CONTRACT_ID = process.env.DUELS_CONTRACT_ID!;
 export async function GET(request: Request) {
   try {
     const { searchParams } = new URL(request.url);
+    const fromAccount = searchParams.get("fromAccount");
     const count = searchParams.get("count");
-    const offset = searchParams.get("offset");
 
     const url = `https://rpc.mainnet.near.org`;
     const provider = new providers.JsonRpcProvider({ url });
 
     const args = {
+      from_account: fromAccount,
       count: parseInt(count || "10"),
-      offset: parseInt(offset || "0"),
     };
 
     const res = await provider.query({
@@ -0,0 +1,56 @@
+import { NextResponse } from "next/server";
+
+const DUELS_CONTRACT_ID = process.env.DUELS_CONTRACT_ID!;
+
+export async function POST(request: Request) {
+  try {
+    const { searchParams } = new URL(request.url);
+    const duelId = searchParams.get("duelId");
+    const secret = searchParams.get("secret");
+
+    if (!duelId || !secret) {
+      return NextResponse.json(
+        { error: "duelId and secret are required parameters" },
+        { status: 400 },
+      );
+    }
+
+    // Secrets are handed out in hex so they survive a query string
+    const bytes = Buffer.from(secret, "hex");
+    if (bytes.length !== 32) {
+      return NextResponse.json(
+        {
+          error:
+            "secret must be the 32-byte hex secret returned when the duel was created, accepted or committed to",
+        },
+        { status: 400 },
+      );
+    }
+
+    const transactionPayload = {
+      actions: [
+        {
+          type: "FunctionCall",
+          params: {
+            account_id: DUELS_CONTRACT_ID,
+            methodName: "reveal_secret",
+            args: {
+              duel_id: duelId,
+              secret: bytes.toString("base64"),
+            },
+            deposit: "1",
+            gas: "30000000000000",
+          },
+        },
+      ],
+    };
+
+    return NextResponse.json({ transactionPayload });
+  } catch (error) {
+    console.error("Error generating NEAR transaction payload:", error);
+    return NextResponse.json(
+      { error: "Failed to generate NEAR transaction payload" },
+      { status: 500 },
+    );
+  }
+}
@@ -7,6 +7,7 @@ export async function POST(request: Request) {
     const { searchParams } = new URL(request.url);
     const duelId = searchParams.get("duelId");
     const style = searchParams.get("style");
+    const signature = searchParams.get("signature") === "true";
 
     if (!duelId || !style) {
       return NextResponse.json(
@@ -21,11 +22,12 @@ export async function POST(request: Request) {
           type: "FunctionCall",
           params: {
             account_id: DUELS_CONTRACT_ID,
-            methodName: "take_turn",
+            methodName: signature ? "take_signature_turn" : "take_turn",
             args: {
               duel_id: duelId,
               style,
             },
+            deposit: "1",
             gas: "30000000000000",
           },
         },